@@ -1,16 +1,24 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 
+use darling::ast::{Data, Fields, Style};
+use darling::util::Override;
 use darling::{FromDeriveInput, FromField, FromMeta, FromVariant};
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::parse_macro_input;
 
 mod casing;
 
 use crate::casing::{transform_field_casing, transform_variant_casing};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, FromMeta)]
+/// The property holding the payload of an internally tagged newtype variant,
+/// when that payload is not an entity (and so has no properties to store besides the tag).
+const PAYLOAD_PROPERTY: &str = "$value";
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromMeta)]
 pub(crate) enum RenameAll {
     #[darling(rename = "lowercase")]
     LowerCase,
@@ -19,6 +27,7 @@ pub(crate) enum RenameAll {
     #[darling(rename = "PascalCase")]
     PascalCase,
     #[darling(rename = "camelCase")]
+    #[default]
     CamelCase,
     #[darling(rename = "snake_case")]
     SnakeCase,
@@ -30,102 +39,577 @@ pub(crate) enum RenameAll {
     ScreamingKebabCase,
 }
 
-impl Default for RenameAll {
-    fn default() -> RenameAll {
-        RenameAll::CamelCase
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, FromDeriveInput)]
-#[darling(attributes(datastore), supports(struct_named, enum_unit))]
+#[darling(attributes(datastore), supports(struct_any, enum_any))]
 struct Container {
     pub ident: syn::Ident,
-    // pub vis: syn::Visibility,
-    // pub generics: syn::Generics,
-    pub data: darling::ast::Data<VariantContainer, FieldContainer>,
+    pub vis: syn::Visibility,
+    pub generics: syn::Generics,
+    pub data: Data<VariantContainer, FieldContainer>,
     // pub attrs: Vec<syn::Attribute>,
     #[darling(default)]
     pub rename_all: RenameAll,
+    #[darling(default)]
+    pub tag: Option<String>,
+    #[darling(default)]
+    pub content: Option<String>,
+    #[darling(default)]
+    pub kind: Option<String>,
+    #[darling(default)]
+    pub namespace: Option<String>,
+    #[darling(default)]
+    pub id: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, FromVariant)]
 #[darling(attributes(datastore))]
 struct VariantContainer {
     pub ident: syn::Ident,
+    pub fields: Fields<FieldContainer>,
     #[darling(default)]
     pub rename: Option<String>,
+    #[darling(default)]
+    pub rename_all: RenameAll,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, FromField)]
 #[darling(attributes(datastore))]
 struct FieldContainer {
     pub ident: Option<syn::Ident>,
+    pub ty: syn::Type,
     #[darling(default)]
     pub rename: Option<String>,
+    #[darling(default)]
+    pub exclude_from_indexes: bool,
+    #[darling(default)]
+    pub skip: bool,
+    #[darling(default)]
+    pub default: Option<Override<syn::Path>>,
+    #[darling(default)]
+    pub flatten: bool,
+    #[darling(default)]
+    pub with: Option<syn::Path>,
+    #[darling(default)]
+    pub key: bool,
+}
+
+impl VariantContainer {
+    /// The stored name of the variant, after applying `rename` and `rename_all`.
+    fn name(&self, rename_all: RenameAll) -> syn::LitStr {
+        let variant = self.ident.clone();
+        let span = variant.span();
+        let name = self
+            .rename
+            .clone()
+            .unwrap_or_else(|| transform_variant_casing(variant, rename_all));
+        syn::LitStr::new(name.as_str(), span)
+    }
+}
+
+impl FieldContainer {
+    /// The property name of a named field, after applying `rename` and `rename_all`.
+    fn name(&self, rename_all: RenameAll) -> syn::LitStr {
+        let field = self.ident.clone().unwrap();
+        let span = field.span();
+        let name = self
+            .rename
+            .clone()
+            .unwrap_or_else(|| transform_field_casing(field, rename_all));
+        syn::LitStr::new(name.as_str(), span)
+    }
+
+    /// The expression producing the default value of the field, if it has one.
+    fn default_expr(&self) -> Option<TokenStream2> {
+        match &self.default {
+            Some(Override::Explicit(path)) => Some(quote!(#path())),
+            Some(Override::Inherit) => Some(quote!(::std::default::Default::default())),
+            None if self.skip => Some(quote!(::std::default::Default::default())),
+            None => None,
+        }
+    }
+
+    /// The expression converting `expr` (of the field's type) into a `Value`.
+    fn convert_into(&self, expr: TokenStream2) -> TokenStream2 {
+        let ty = &self.ty;
+        match &self.with {
+            Some(with) => quote!(#with::into_value(#expr)),
+            None => quote!(<#ty as ::google_cloud::datastore::IntoValue>::into_value(#expr)),
+        }
+    }
+
+    /// The expression converting `expr` (a `Value`) into the field's type, propagating errors.
+    fn convert_from(&self, expr: TokenStream2) -> TokenStream2 {
+        let ty = &self.ty;
+        match &self.with {
+            Some(with) => quote!(#with::from_value(#expr)?),
+            None => quote!(<#ty as ::google_cloud::datastore::FromValue>::from_value(#expr)?),
+        }
+    }
+}
+
+/// Checks whether a type mentions one of the generic type parameters of the container.
+fn mentions_params(ty: &syn::Type, params: &[syn::Ident]) -> bool {
+    fn visit(tokens: TokenStream2, params: &[syn::Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) => params.contains(&ident),
+            proc_macro2::TokenTree::Group(group) => visit(group.stream(), params),
+            _ => false,
+        })
+    }
+    visit(quote!(#ty), params)
+}
+
+/// Adds `bound` to the where clause for every field type that depends on a generic type parameter.
+fn bounded_generics<'a>(
+    generics: &syn::Generics,
+    fields: impl IntoIterator<Item = (&'a syn::Type, TokenStream2)>,
+) -> syn::Generics {
+    let params: Vec<syn::Ident> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let mut generics = generics.clone();
+    let where_clause = generics.make_where_clause();
+    for (ty, bound) in fields {
+        if mentions_params(ty, &params) {
+            where_clause.predicates.push(syn::parse_quote!(#ty: #bound));
+        }
+    }
+    generics
+}
+
+fn into_value_bounds<'a>(
+    fields: impl IntoIterator<Item = &'a FieldContainer>,
+) -> Vec<(&'a syn::Type, TokenStream2)> {
+    fields
+        .into_iter()
+        .filter(|field| !field.skip && !field.key && field.with.is_none())
+        .map(|field| (&field.ty, quote!(::google_cloud::datastore::IntoValue)))
+        .collect()
+}
+
+fn from_value_bounds<'a>(
+    fields: impl IntoIterator<Item = &'a FieldContainer>,
+) -> Vec<(&'a syn::Type, TokenStream2)> {
+    let mut bounds = Vec::new();
+    for field in fields.into_iter().filter(|field| !field.key) {
+        if field.default == Some(Override::Inherit) || (field.skip && field.default.is_none()) {
+            bounds.push((&field.ty, quote!(::std::default::Default)));
+        }
+        if !field.skip && field.with.is_none() {
+            bounds.push((&field.ty, quote!(::google_cloud::datastore::FromValue)));
+        }
+    }
+    bounds
+}
+
+fn key_field(fields: &Fields<FieldContainer>) -> Result<Option<syn::Ident>, syn::Error> {
+    let mut keys = fields.fields.iter().filter(|field| field.key);
+    let key = match keys.next() {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    match (&key.ident, keys.next()) {
+        (Some(ident), None) => Ok(Some(ident.clone())),
+        (None, _) => Err(syn::Error::new_spanned(
+            &key.ty,
+            "`#[datastore(key)]` is only supported on named fields",
+        )),
+        (Some(_), Some(other)) => Err(syn::Error::new_spanned(
+            &other.ident,
+            "only one field can be marked with `#[datastore(key)]`",
+        )),
+    }
+}
+
+/// Builds the statements inserting the named fields into `props`.
+///
+/// `access` produces the expression evaluating to the value of a given field.
+fn insert_named_fields(
+    fields: &[FieldContainer],
+    rename_all: RenameAll,
+    access: impl Fn(usize, &FieldContainer) -> TokenStream2,
+) -> Vec<TokenStream2> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !field.skip && !field.key)
+        .map(|(index, field)| {
+            let converted = field.convert_into(access(index, field));
+            let name = field.name(rename_all);
+            if field.flatten {
+                //? Values which are not entities (like `None`) have no properties to flatten,
+                //? so they are kept under the field's name instead.
+                quote! {
+                    match #converted {
+                        ::google_cloud::datastore::Value::EntityValue(inner) => props.extend(inner),
+                        value => {
+                            props.insert(::std::string::String::from(#name), value);
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    props.insert(::std::string::String::from(#name), #converted);
+                }
+            }
+        })
+        .collect()
+}
+
+/// Builds the statements collecting the paths, within the named fields, that are excluded from indexes.
+///
+/// Every collected path is prefixed with `prefix`.
+fn exclude_named_fields(
+    fields: &[FieldContainer],
+    rename_all: RenameAll,
+    prefix: &str,
+) -> Vec<TokenStream2> {
+    let mut exclusions = Vec::new();
+    for field in fields.iter().filter(|field| !field.skip && !field.key) {
+        let ty = &field.ty;
+        if field.flatten {
+            if field.with.is_none() {
+                exclusions.push(quote! {
+                    excluded.extend(
+                        <#ty as ::google_cloud::datastore::IntoValue>::excluded_from_indexes()
+                            .into_iter()
+                            .map(|path| ::std::format!("{}{}", #prefix, path)),
+                    );
+                });
+            }
+            continue;
+        }
+        let name = field.name(rename_all);
+        let path = syn::LitStr::new(&format!("{}{}", prefix, name.value()), name.span());
+        if field.exclude_from_indexes {
+            exclusions.push(quote! {
+                excluded.push(::std::string::String::from(#path));
+            });
+        }
+        if field.with.is_none() {
+            exclusions.push(quote! {
+                excluded.extend(
+                    <#ty as ::google_cloud::datastore::IntoValue>::excluded_from_indexes()
+                        .into_iter()
+                        .map(|path| ::std::format!("{}.{}", #path, path)),
+                );
+            });
+        }
+    }
+    exclusions
 }
 
 fn derive_into_value_struct(
     ident: syn::Ident,
-    fields: Vec<FieldContainer>,
+    generics: syn::Generics,
+    fields: Fields<FieldContainer>,
     rename_all: RenameAll,
 ) -> TokenStream {
-    let idents: Vec<syn::Ident> = fields
-        .iter()
-        .map(|field| field.ident.clone().unwrap())
-        .collect();
-    let names: Vec<syn::LitStr> = fields
-        .into_iter()
-        .map(|field| {
-            let renamed = field.rename;
-            let field = field.ident.unwrap();
-            let span = field.span();
-            let name = renamed.unwrap_or_else(|| transform_field_casing(field, rename_all));
-            syn::LitStr::new(name.as_str(), span)
-        })
-        .collect();
+    let key = match key_field(&fields) {
+        Ok(key) => key,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let generics = bounded_generics(&generics, into_value_bounds(&fields.fields));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let capacity = names.len();
+    let (into_value, excluded_from_indexes) = match fields.style {
+        Style::Struct => {
+            let inserts = insert_named_fields(&fields.fields, rename_all, |_, field| {
+                let member = field.ident.clone().unwrap();
+                quote!(self.#member)
+            });
+            let exclusions = exclude_named_fields(&fields.fields, rename_all, "");
+            let into_value = quote! {
+                let mut props = ::std::collections::HashMap::new();
+                #(#inserts)*
+                ::google_cloud::datastore::Value::EntityValue(props)
+            };
+            let excluded_from_indexes = quote! {
+                let mut excluded = ::std::vec::Vec::new();
+                #(#exclusions)*
+                excluded
+            };
+            (into_value, excluded_from_indexes)
+        }
+        Style::Tuple if fields.fields.len() == 1 => {
+            let field = &fields.fields[0];
+            let ty = &field.ty;
+            let into_value = field.convert_into(quote!(self.0));
+            let excluded_from_indexes = match field.with {
+                Some(_) => quote!(::std::vec::Vec::new()),
+                None => {
+                    quote!(<#ty as ::google_cloud::datastore::IntoValue>::excluded_from_indexes())
+                }
+            };
+            (into_value, excluded_from_indexes)
+        }
+        Style::Tuple => {
+            let values = fields
+                .fields
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| !field.skip)
+                .map(|(index, field)| {
+                    let index = syn::Index::from(index);
+                    field.convert_into(quote!(self.#index))
+                });
+            let into_value = quote! {
+                ::google_cloud::datastore::Value::ArrayValue(::std::vec![#(#values),*])
+            };
+            (into_value, quote!(::std::vec::Vec::new()))
+        }
+        Style::Unit => {
+            let into_value = quote! {
+                ::google_cloud::datastore::Value::EntityValue(::std::collections::HashMap::new())
+            };
+            (into_value, quote!(::std::vec::Vec::new()))
+        }
+    };
+
+    let into_entity = key.map(|key| {
+        quote! {
+            impl #impl_generics ::google_cloud::datastore::IntoEntity for #ident #ty_generics #where_clause {
+                fn into_entity(self) -> ::std::result::Result<::google_cloud::datastore::Entity, ::google_cloud::error::ConvertError> {
+                    let key = ::std::clone::Clone::clone(&self.#key);
+                    ::google_cloud::datastore::Entity::new(key, self)
+                }
+            }
+        }
+    });
 
     let tokens = quote! {
-        impl ::google_cloud::datastore::IntoValue for #ident {
+        impl #impl_generics ::google_cloud::datastore::IntoValue for #ident #ty_generics #where_clause {
             fn into_value(self) -> ::google_cloud::datastore::Value {
-                let mut props = ::std::collections::HashMap::with_capacity(#capacity);
-                #(props.insert(::std::string::String::from(#names), self.#idents.into_value());)*
-                ::google_cloud::datastore::Value::EntityValue(props)
+                #into_value
+            }
+
+            fn excluded_from_indexes() -> ::std::vec::Vec<::std::string::String> {
+                #excluded_from_indexes
             }
         }
+
+        #into_entity
     };
 
     tokens.into()
 }
 
+/// Checks the tagging attributes of an enum, returning its `tag` and `content` property names.
+fn enum_tagging(
+    ident: &syn::Ident,
+    variants: &[VariantContainer],
+    tag: Option<String>,
+    content: Option<String>,
+) -> Result<Option<(String, Option<String>)>, syn::Error> {
+    let tagging = match (tag, content) {
+        (Some(tag), content) => (tag, content),
+        (None, Some(_)) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "`#[datastore(content = \"...\")]` requires `#[datastore(tag = \"...\")]`",
+            ))
+        }
+        (None, None) => {
+            return match variants.iter().find(|variant| !variant.fields.is_unit()) {
+                Some(variant) => Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "enums with data-carrying variants require `#[datastore(tag = \"...\")]`",
+                )),
+                None => Ok(None),
+            };
+        }
+    };
+    for variant in variants {
+        if let Some(field) = variant.fields.iter().find(|field| field.key) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`#[datastore(key)]` is not supported within enum variants",
+            ));
+        }
+        if tagging.1.is_none() && variant.fields.style == Style::Struct {
+            let clashing = variant.fields.iter().find(|field| {
+                !field.skip
+                    && !field.key
+                    && !field.flatten
+                    && field.name(variant.rename_all).value() == tagging.0
+            });
+            if let Some(field) = clashing {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "the field is stored under the same property as the enum's tag",
+                ));
+            }
+        }
+        if tagging.1.is_none() && variant.fields.style == Style::Tuple && variant.fields.len() > 1 {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                "tuple variants require an adjacently tagged enum, using `#[datastore(content = \"...\")]`",
+            ));
+        }
+    }
+    Ok(Some(tagging))
+}
+
 fn derive_into_value_enum(
     ident: syn::Ident,
+    generics: syn::Generics,
     variants: Vec<VariantContainer>,
     rename_all: RenameAll,
+    tagging: Option<(String, Option<String>)>,
 ) -> TokenStream {
-    let idents: Vec<syn::Ident> = variants
-        .iter()
-        .map(|variant| variant.ident.clone())
-        .collect();
-    let names: Vec<syn::LitStr> = variants
-        .into_iter()
-        .map(|variant| {
-            let renamed = variant.rename;
-            let variant = variant.ident;
-            let span = variant.span();
-            let name = renamed.unwrap_or_else(|| transform_variant_casing(variant, rename_all));
-            syn::LitStr::new(name.as_str(), span)
-        })
-        .collect();
+    let generics = bounded_generics(
+        &generics,
+        into_value_bounds(variants.iter().flat_map(|variant| variant.fields.iter())),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (tag, content) = match tagging {
+        Some(tagging) => tagging,
+        None => {
+            let idents = variants.iter().map(|variant| &variant.ident);
+            let names = variants.iter().map(|variant| variant.name(rename_all));
+            let tokens = quote! {
+                impl #impl_generics ::google_cloud::datastore::IntoValue for #ident #ty_generics #where_clause {
+                    fn into_value(self) -> ::google_cloud::datastore::Value {
+                        match self {
+                            #(#ident::#idents => ::google_cloud::datastore::Value::StringValue(#names.to_string()),)*
+                        }
+                    }
+                }
+            };
+            return tokens.into();
+        }
+    };
+
+    let prefix = match &content {
+        Some(content) => format!("{}.", content),
+        None => String::new(),
+    };
+    let mut arms = Vec::new();
+    let mut exclusions = Vec::new();
+    for variant in variants.iter() {
+        let variant_ident = &variant.ident;
+        let name = variant.name(rename_all);
+        let fields = &variant.fields.fields;
+        let bindings: Vec<syn::Ident> = (0..fields.len())
+            .map(|index| format_ident!("__field{}", index))
+            .collect();
+
+        let (pattern, inserts) = match variant.fields.style {
+            Style::Unit => (quote!(#ident::#variant_ident), quote!()),
+            Style::Struct => {
+                let members = fields
+                    .iter()
+                    .zip(bindings.iter())
+                    .filter(|(field, _)| !field.skip)
+                    .map(|(field, binding)| {
+                        let member = field.ident.clone().unwrap();
+                        quote!(#member: #binding)
+                    });
+                let pattern = quote!(#ident::#variant_ident { #(#members,)* .. });
+                let inserts = insert_named_fields(fields, variant.rename_all, |index, _| {
+                    let binding = &bindings[index];
+                    quote!(#binding)
+                });
+                exclusions.extend(exclude_named_fields(fields, variant.rename_all, &prefix));
+                let inserts = match &content {
+                    Some(content) => quote! {
+                        let inner = {
+                            let mut props = ::std::collections::HashMap::new();
+                            #(#inserts)*
+                            props
+                        };
+                        props.insert(
+                            ::std::string::String::from(#content),
+                            ::google_cloud::datastore::Value::EntityValue(inner),
+                        );
+                    },
+                    None => quote!(#(#inserts)*),
+                };
+                (pattern, inserts)
+            }
+            Style::Tuple if fields.len() == 1 => {
+                let field = &fields[0];
+                let ty = &field.ty;
+                let pattern = quote!(#ident::#variant_ident(__field0));
+                let converted = field.convert_into(quote!(__field0));
+                if field.with.is_none() {
+                    exclusions.push(quote! {
+                        excluded.extend(
+                            <#ty as ::google_cloud::datastore::IntoValue>::excluded_from_indexes()
+                                .into_iter()
+                                .map(|path| ::std::format!("{}{}", #prefix, path)),
+                        );
+                    });
+                }
+                let inserts = match &content {
+                    Some(content) => quote! {
+                        props.insert(::std::string::String::from(#content), #converted);
+                    },
+                    None => quote! {
+                        match #converted {
+                            ::google_cloud::datastore::Value::EntityValue(inner) => props.extend(inner),
+                            value => {
+                                props.insert(::std::string::String::from(#PAYLOAD_PROPERTY), value);
+                            }
+                        }
+                    },
+                };
+                (pattern, inserts)
+            }
+            Style::Tuple => {
+                let patterns = fields.iter().zip(bindings.iter()).map(|(field, binding)| {
+                    if field.skip {
+                        quote!(_)
+                    } else {
+                        quote!(#binding)
+                    }
+                });
+                let pattern = quote!(#ident::#variant_ident(#(#patterns),*));
+                let values = fields
+                    .iter()
+                    .zip(bindings.iter())
+                    .filter(|(field, _)| !field.skip)
+                    .map(|(field, binding)| field.convert_into(quote!(#binding)));
+                // `enum_tagging` ensures that tuple variants are adjacently tagged.
+                let content = content.as_ref().unwrap();
+                let inserts = quote! {
+                    props.insert(
+                        ::std::string::String::from(#content),
+                        ::google_cloud::datastore::Value::ArrayValue(::std::vec![#(#values),*]),
+                    );
+                };
+                (pattern, inserts)
+            }
+        };
+
+        arms.push(quote! {
+            #pattern => {
+                let mut props = ::std::collections::HashMap::new();
+                #inserts
+                props.insert(
+                    ::std::string::String::from(#tag),
+                    ::google_cloud::datastore::Value::StringValue(::std::string::String::from(#name)),
+                );
+                ::google_cloud::datastore::Value::EntityValue(props)
+            }
+        });
+    }
 
     let tokens = quote! {
-        impl ::google_cloud::datastore::IntoValue for #ident {
+        impl #impl_generics ::google_cloud::datastore::IntoValue for #ident #ty_generics #where_clause {
             fn into_value(self) -> ::google_cloud::datastore::Value {
                 match self {
-                    #(#ident::#idents => ::google_cloud::datastore::Value::StringValue(#names.to_string()),)*
+                    #(#arms)*
                 }
             }
+
+            fn excluded_from_indexes() -> ::std::vec::Vec<::std::string::String> {
+                let mut excluded = ::std::vec::Vec::new();
+                #(#exclusions)*
+                excluded
+            }
         }
     };
 
@@ -135,63 +619,186 @@ fn derive_into_value_enum(
 #[proc_macro_derive(IntoValue, attributes(datastore))]
 pub fn derive_into_value(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
-    let container = Container::from_derive_input(&input).unwrap();
+    let container = match Container::from_derive_input(&input) {
+        Ok(container) => container,
+        Err(err) => return err.write_errors().into(),
+    };
 
     let ident = container.ident;
     let rename_all = container.rename_all;
 
     match container.data {
-        darling::ast::Data::Enum(variants) => derive_into_value_enum(ident, variants, rename_all),
-        darling::ast::Data::Struct(darling::ast::Fields { fields, .. }) => {
-            derive_into_value_struct(ident, fields, rename_all)
+        Data::Enum(variants) => {
+            match enum_tagging(&ident, &variants, container.tag, container.content) {
+                Ok(tagging) => {
+                    derive_into_value_enum(ident, container.generics, variants, rename_all, tagging)
+                }
+                Err(err) => err.to_compile_error().into(),
+            }
+        }
+        Data::Struct(fields) => {
+            derive_into_value_struct(ident, container.generics, fields, rename_all)
+        }
+    }
+}
+
+/// Builds the expression constructing `path` (a struct or a struct variant) out of `props`,
+/// the properties of an entity value.
+fn construct_from_props(
+    path: TokenStream2,
+    fields: &[FieldContainer],
+    rename_all: RenameAll,
+) -> TokenStream2 {
+    let mut bindings = Vec::new();
+    let mut flattened = Vec::new();
+    let mut members = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let member = field.ident.clone().unwrap();
+        let binding = format_ident!("__field{}", index);
+        members.push(quote!(#member: #binding));
+        if field.key {
+            bindings.push(quote!(let #binding = key;));
+        } else if field.skip {
+            let default = field.default_expr().unwrap();
+            bindings.push(quote!(let #binding = #default;));
+        } else if field.flatten {
+            let name = field.name(rename_all);
+            let converted_value = field.convert_from(quote!(prop));
+            let converted_props = field.convert_from(quote! {
+                ::google_cloud::datastore::Value::EntityValue(::std::clone::Clone::clone(&props))
+            });
+            flattened.push(quote! {
+                let #binding = match props.get(#name) {
+                    ::std::option::Option::Some(::google_cloud::datastore::Value::EntityValue(_))
+                    | ::std::option::Option::None => ::std::option::Option::None,
+                    ::std::option::Option::Some(_) => props.remove(#name),
+                };
+                let #binding = match #binding {
+                    ::std::option::Option::Some(prop) => #converted_value,
+                    ::std::option::Option::None => #converted_props,
+                };
+            });
+        } else {
+            let name = field.name(rename_all);
+            let converted = field.convert_from(quote!(prop));
+            let missing = match (field.default_expr(), &field.with) {
+                (Some(default), _) => default,
+                (None, Some(_)) => quote! {
+                    return ::std::result::Result::Err(
+                        ::google_cloud::error::ConvertError::MissingProperty(::std::string::String::from(#name))
+                    )
+                },
+                (None, None) => {
+                    let ty = &field.ty;
+                    quote!(<#ty as ::google_cloud::datastore::FromValue>::from_missing(#name)?)
+                }
+            };
+            bindings.push(quote! {
+                let #binding = match props.remove(#name) {
+                    ::std::option::Option::Some(prop) => #converted,
+                    ::std::option::Option::None => #missing,
+                };
+            });
+        }
+    }
+
+    // Flattened fields are constructed last, from the properties not claimed by any other field.
+    quote! {{
+        #(#bindings)*
+        #(#flattened)*
+        #path { #(#members),* }
+    }}
+}
+
+/// Builds the expression converting `value`, expected to be an array,
+/// into `path` (a tuple struct or a tuple variant).
+fn construct_from_array(path: TokenStream2, fields: &[FieldContainer]) -> TokenStream2 {
+    let len = fields.iter().filter(|field| !field.skip).count();
+    let elements = fields.iter().map(|field| match field.default_expr() {
+        Some(default) if field.skip => default,
+        _ => field.convert_from(quote!(values.next().unwrap())),
+    });
+    quote! {
+        match value {
+            ::google_cloud::datastore::Value::ArrayValue(values) if values.len() == #len => {
+                let mut values = values.into_iter();
+                ::std::result::Result::Ok(#path(#(#elements),*))
+            }
+            _ => ::std::result::Result::Err(
+                ::google_cloud::error::ConvertError::UnexpectedPropertyType {
+                    expected: ::std::format!("array of {} values", #len),
+                    got: ::std::string::String::from(value.type_name()),
+                }
+            ),
         }
     }
 }
 
+/// Builds the statement binding `props` to the properties of `value`, expected to be an entity.
+fn entity_props() -> TokenStream2 {
+    quote! {
+        let mut props = match value {
+            ::google_cloud::datastore::Value::EntityValue(props) => props,
+            _ => return ::std::result::Result::Err(
+                ::google_cloud::error::ConvertError::UnexpectedPropertyType {
+                    expected: ::std::string::String::from("entity"),
+                    got: ::std::string::String::from(value.type_name()),
+                }
+            ),
+        };
+    }
+}
+
 fn derive_from_value_struct(
     ident: syn::Ident,
-    fields: Vec<FieldContainer>,
+    generics: syn::Generics,
+    fields: Fields<FieldContainer>,
     rename_all: RenameAll,
 ) -> TokenStream {
-    let idents: Vec<syn::Ident> = fields
-        .iter()
-        .map(|field| field.ident.clone().unwrap())
-        .collect();
-    let names: Vec<syn::LitStr> = fields
-        .into_iter()
-        .map(|field| {
-            let renamed = field.rename;
-            let field = field.ident.unwrap();
-            let span = field.span();
-            let name = renamed.unwrap_or_else(|| transform_field_casing(field, rename_all));
-            syn::LitStr::new(name.as_str(), span)
-        })
-        .collect();
+    let key = match key_field(&fields) {
+        Ok(key) => key,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let generics = bounded_generics(&generics, from_value_bounds(&fields.fields));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let tokens = quote! {
-        impl ::google_cloud::datastore::FromValue for #ident {
-            fn from_value(value: ::google_cloud::datastore::Value) -> ::std::result::Result<#ident, ::google_cloud::error::ConvertError> {
-                let mut props = match value {
-                    ::google_cloud::datastore::Value::EntityValue(props) => props,
-                    _ => return ::std::result::Result::Err(
-                        ::google_cloud::error::ConvertError::UnexpectedPropertyType {
-                            expected: ::std::string::String::from("entity"),
-                            got: ::std::string::String::from(value.type_name()),
+    let from_value = match fields.style {
+        Style::Struct => {
+            let construct = construct_from_props(quote!(#ident), &fields.fields, rename_all);
+            let props = entity_props();
+            if key.is_some() {
+                let tokens = quote! {
+                    impl #impl_generics ::google_cloud::datastore::FromEntity for #ident #ty_generics #where_clause {
+                        fn from_entity(entity: ::google_cloud::datastore::Entity) -> ::std::result::Result<Self, ::google_cloud::error::ConvertError> {
+                            let key = ::std::clone::Clone::clone(entity.key());
+                            let value = entity.into_properties();
+                            #props
+                            ::std::result::Result::Ok(#construct)
                         }
-                    ),
+                    }
                 };
-                let value = #ident {
-                    #(#idents: {
-                        let prop = props
-                            .remove(#names)
-                            .ok_or_else(|| {
-                                ::google_cloud::error::ConvertError::MissingProperty(::std::string::String::from(#names))
-                            })?;
-                        let value = ::google_cloud::datastore::FromValue::from_value(prop)?;
-                        value
-                    },)*
-                };
-                ::std::result::Result::Ok(value)
+                return tokens.into();
+            }
+            quote! {
+                #props
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+        Style::Tuple if fields.fields.len() == 1 => {
+            let converted = fields.fields[0].convert_from(quote!(value));
+            quote!(::std::result::Result::Ok(#ident(#converted)))
+        }
+        Style::Tuple => construct_from_array(quote!(#ident), &fields.fields),
+        Style::Unit => quote! {
+            let _ = value;
+            ::std::result::Result::Ok(#ident)
+        },
+    };
+
+    let tokens = quote! {
+        impl #impl_generics ::google_cloud::datastore::FromValue for #ident #ty_generics #where_clause {
+            fn from_value(value: ::google_cloud::datastore::Value) -> ::std::result::Result<Self, ::google_cloud::error::ConvertError> {
+                #from_value
             }
         }
     };
@@ -201,59 +808,217 @@ fn derive_from_value_struct(
 
 fn derive_from_value_enum(
     ident: syn::Ident,
+    generics: syn::Generics,
     variants: Vec<VariantContainer>,
     rename_all: RenameAll,
+    tagging: Option<(String, Option<String>)>,
 ) -> TokenStream {
-    let idents: Vec<syn::Ident> = variants
-        .iter()
-        .map(|variant| variant.ident.clone())
-        .collect();
+    let generics = bounded_generics(
+        &generics,
+        from_value_bounds(variants.iter().flat_map(|variant| variant.fields.iter())),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     let names: Vec<syn::LitStr> = variants
-        .into_iter()
-        .map(|variant| {
-            let renamed = variant.rename;
-            let variant = variant.ident;
-            let span = variant.span();
-            let name = renamed.unwrap_or_else(|| transform_variant_casing(variant, rename_all));
-            syn::LitStr::new(name.as_str(), span)
-        })
+        .iter()
+        .map(|variant| variant.name(rename_all))
         .collect();
 
-    let tokens = quote! {
-        impl ::google_cloud::datastore::FromValue for #ident {
-            fn from_value(value: ::google_cloud::datastore::Value) -> ::std::result::Result<#ident, ::google_cloud::error::ConvertError> {
+    let from_value = match tagging {
+        None => {
+            let idents = variants.iter().map(|variant| &variant.ident);
+            quote! {
                 let value = match value {
                     ::google_cloud::datastore::Value::StringValue(value) => value,
                     _ => return ::std::result::Result::Err(
                         ::google_cloud::error::ConvertError::UnexpectedPropertyType {
-                            expected: ::std::string::String::from("entity"),
+                            expected: ::std::string::String::from("string"),
                             got: ::std::string::String::from(value.type_name()),
                         }
                     ),
                 };
                 match value.as_str() {
                     #(#names => ::std::result::Result::Ok(#ident::#idents),)*
-                    _ => todo!("[datastore-derive] unknown enum variant encountered"),
+                    _ => ::std::result::Result::Err(
+                        ::google_cloud::error::ConvertError::UnknownVariant(value)
+                    ),
+                }
+            }
+        }
+        Some((tag, content)) => {
+            let bodies = variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                let path = quote!(#ident::#variant_ident);
+                let fields = &variant.fields.fields;
+                let value = match (&variant.fields.style, &content) {
+                    (Style::Unit, _) => return quote!(::std::result::Result::Ok(#path)),
+                    (_, Some(content)) => quote! {
+                        let value = match props.remove(#content) {
+                            ::std::option::Option::Some(value) => value,
+                            ::std::option::Option::None => return ::std::result::Result::Err(
+                                ::google_cloud::error::ConvertError::MissingProperty(::std::string::String::from(#content))
+                            ),
+                        };
+                    },
+                    // Newtype payloads which are not entities are stored under a dedicated property.
+                    (Style::Tuple, None) => quote! {
+                        let value = match props.remove(#PAYLOAD_PROPERTY) {
+                            ::std::option::Option::Some(value) => value,
+                            ::std::option::Option::None => ::google_cloud::datastore::Value::EntityValue(props),
+                        };
+                    },
+                    // Internally tagged variants are read from the properties left besides the tag.
+                    (_, None) => quote! {
+                        let value = ::google_cloud::datastore::Value::EntityValue(props);
+                    },
+                };
+                let construct = match variant.fields.style {
+                    Style::Struct => {
+                        let props = entity_props();
+                        let construct = construct_from_props(path, fields, variant.rename_all);
+                        quote! {
+                            #props
+                            ::std::result::Result::Ok(#construct)
+                        }
+                    }
+                    Style::Tuple if fields.len() == 1 => {
+                        let converted = fields[0].convert_from(quote!(value));
+                        quote!(::std::result::Result::Ok(#path(#converted)))
+                    }
+                    _ => construct_from_array(path, fields),
+                };
+                quote! {{
+                    #value
+                    #construct
+                }}
+            });
+            let props = entity_props();
+            quote! {
+                #props
+                let tag = match props.remove(#tag) {
+                    ::std::option::Option::Some(::google_cloud::datastore::Value::StringValue(tag)) => tag,
+                    ::std::option::Option::Some(value) => return ::std::result::Result::Err(
+                        ::google_cloud::error::ConvertError::UnexpectedPropertyType {
+                            expected: ::std::string::String::from("string"),
+                            got: ::std::string::String::from(value.type_name()),
+                        }
+                    ),
+                    ::std::option::Option::None => return ::std::result::Result::Err(
+                        ::google_cloud::error::ConvertError::MissingProperty(::std::string::String::from(#tag))
+                    ),
+                };
+                match tag.as_str() {
+                    #(#names => #bodies,)*
+                    _ => ::std::result::Result::Err(
+                        ::google_cloud::error::ConvertError::UnknownVariant(tag)
+                    ),
                 }
             }
         }
     };
 
+    let tokens = quote! {
+        impl #impl_generics ::google_cloud::datastore::FromValue for #ident #ty_generics #where_clause {
+            fn from_value(value: ::google_cloud::datastore::Value) -> ::std::result::Result<Self, ::google_cloud::error::ConvertError> {
+                #from_value
+            }
+        }
+    };
+
     tokens.into()
 }
 
+/// Derives `FromValue`, or only `FromEntity` for structs with a `#[datastore(key)]` field
+/// (which need the key of an entity, and so cannot be converted from a lone value).
 #[proc_macro_derive(FromValue, attributes(datastore))]
 pub fn derive_from_value(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
-    let container = Container::from_derive_input(&input).unwrap();
+    let container = match Container::from_derive_input(&input) {
+        Ok(container) => container,
+        Err(err) => return err.write_errors().into(),
+    };
 
     let ident = container.ident;
     let rename_all = container.rename_all;
 
     match container.data {
-        darling::ast::Data::Enum(variants) => derive_from_value_enum(ident, variants, rename_all),
-        darling::ast::Data::Struct(darling::ast::Fields { fields, .. }) => {
-            derive_from_value_struct(ident, fields, rename_all)
+        Data::Enum(variants) => {
+            match enum_tagging(&ident, &variants, container.tag, container.content) {
+                Ok(tagging) => {
+                    derive_from_value_enum(ident, container.generics, variants, rename_all, tagging)
+                }
+                Err(err) => err.to_compile_error().into(),
+            }
+        }
+        Data::Struct(fields) => {
+            derive_from_value_struct(ident, container.generics, fields, rename_all)
         }
     }
 }
+
+#[proc_macro_derive(Model, attributes(datastore))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let container = match Container::from_derive_input(&input) {
+        Ok(container) => container,
+        Err(err) => return err.write_errors().into(),
+    };
+
+    let ident = container.ident;
+    let vis = container.vis;
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+
+    let kind = container.kind.unwrap_or_else(|| ident.to_string());
+    let namespace = match container.namespace {
+        Some(namespace) => quote!(::std::option::Option::Some(#namespace)),
+        None => quote!(::std::option::Option::None),
+    };
+    let id = match container.id {
+        Some(id) => match syn::parse_str::<syn::Type>(id.as_str()) {
+            Ok(id) => quote!(#id),
+            Err(err) => return err.to_compile_error().into(),
+        },
+        None => quote!(i64),
+    };
+
+    // Typed property paths are generated for the named fields stored as properties.
+    let mut properties = Vec::new();
+    if let Data::Struct(fields) = container.data {
+        let stored = fields
+            .fields
+            .into_iter()
+            .filter(|field| field.ident.is_some() && !field.skip && !field.flatten);
+        for field in stored {
+            let (name, ty) = if field.key {
+                let name = syn::LitStr::new("__key__", ident.span());
+                (name, quote!(::google_cloud::datastore::Key))
+            } else {
+                let ty = &field.ty;
+                (field.name(container.rename_all), quote!(#ty))
+            };
+            let member = field.ident.unwrap().to_string();
+            let constant =
+                format_ident!("{}", member.trim_start_matches("r#").to_ascii_uppercase());
+            let doc = format!("The `{}` property of `{}` entities.", name.value(), ident);
+            properties.push(quote! {
+                #[doc = #doc]
+                #vis const #constant: ::google_cloud::datastore::Property<Self, #ty> =
+                    ::google_cloud::datastore::Property::new(#name);
+            });
+        }
+    }
+
+    let tokens = quote! {
+        impl #impl_generics ::google_cloud::datastore::Model for #ident #ty_generics #where_clause {
+            const KIND: &'static str = #kind;
+            const NAMESPACE: ::std::option::Option<&'static str> = #namespace;
+            type Id = #id;
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#properties)*
+        }
+    };
+
+    tokens.into()
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Foo {
+    bar: Option<String>,
+    baz: Option<i64>,
+    qux: bool,
+}
+
+fn main() {
+    let foo = Foo {
+        bar: Some(String::from("test")),
+        baz: None,
+        qux: true,
+    };
+
+    let converted = foo.into_value();
+    match &converted {
+        Value::EntityValue(props) => assert_eq!(props.get("baz"), Some(&Value::NullValue)),
+        _ => panic!("expected an entity value"),
+    }
+
+    //? Both null and missing properties are recovered as `None`.
+    let mut props = HashMap::new();
+    props.insert(String::from("baz"), Value::NullValue);
+    props.insert(String::from("qux"), Value::BooleanValue(false));
+    let recovered = Foo::from_value(Value::EntityValue(props)).unwrap();
+    assert_eq!(
+        recovered,
+        Foo {
+            bar: None,
+            baz: None,
+            qux: false,
+        }
+    );
+
+    //? Non-optional properties are still required.
+    let props = HashMap::new();
+    assert!(Foo::from_value(Value::EntityValue(props)).is_err());
+}
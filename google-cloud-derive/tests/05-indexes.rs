@@ -0,0 +1,33 @@
+use google_cloud::datastore::{Entity, IntoValue, Key};
+
+#[derive(Debug, IntoValue)]
+pub struct Foo {
+    #[datastore(exclude_from_indexes)]
+    bar: String,
+    baz: Vec<Baz>,
+    qux: bool,
+}
+
+#[derive(Debug, IntoValue)]
+pub struct Baz {
+    #[datastore(exclude_from_indexes, rename = "contents")]
+    data: String,
+}
+
+fn main() {
+    let mut excluded = Foo::excluded_from_indexes();
+    excluded.sort();
+    assert_eq!(excluded, vec!["bar", "baz.contents"]);
+
+    let foo = Foo {
+        bar: String::from("test"),
+        baz: vec![Baz {
+            data: String::from("large"),
+        }],
+        qux: true,
+    };
+    let entity = Entity::new(Key::new("foo"), foo).unwrap();
+    assert!(entity.is_excluded_from_indexes("bar"));
+    assert!(entity.is_excluded_from_indexes("baz.contents"));
+    assert!(!entity.is_excluded_from_indexes("qux"));
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Foo {
+    #[datastore(skip)]
+    cache: Vec<String>,
+    #[datastore(default)]
+    count: i64,
+    #[datastore(default = "default_label")]
+    label: String,
+    #[datastore(flatten)]
+    audit: Audit,
+    #[datastore(with = "upper")]
+    code: String,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Audit {
+    #[datastore(rename = "createdBy")]
+    created_by: String,
+    #[datastore(exclude_from_indexes)]
+    note: String,
+}
+
+fn default_label() -> String {
+    String::from("unlabeled")
+}
+
+mod upper {
+    use google_cloud::datastore::{FromValue, IntoValue, Value};
+    use google_cloud::error::ConvertError;
+
+    pub fn into_value(code: String) -> Value {
+        code.to_uppercase().into_value()
+    }
+
+    pub fn from_value(value: Value) -> Result<String, ConvertError> {
+        String::from_value(value).map(|code| code.to_lowercase())
+    }
+}
+
+fn main() {
+    let foo = Foo {
+        cache: vec![String::from("transient")],
+        count: 3,
+        label: String::from("test"),
+        audit: Audit {
+            created_by: String::from("someone"),
+            note: String::from("hello"),
+        },
+        code: String::from("abc"),
+    };
+
+    //? Flattened fields are excluded from indexes without any prefix.
+    assert_eq!(Foo::excluded_from_indexes(), vec!["note"]);
+
+    let converted = foo.into_value();
+    let props = match &converted {
+        Value::EntityValue(props) => props,
+        _ => panic!("expected an entity value"),
+    };
+    assert!(!props.contains_key("cache"));
+    assert!(!props.contains_key("audit"));
+    assert_eq!(
+        props.get("createdBy"),
+        Some(&Value::StringValue(String::from("someone")))
+    );
+    assert_eq!(
+        props.get("code"),
+        Some(&Value::StringValue(String::from("ABC")))
+    );
+
+    let recovered = Foo::from_value(converted).unwrap();
+    assert_eq!(recovered.cache, Vec::<String>::new());
+    assert_eq!(recovered.count, 3);
+    assert_eq!(recovered.audit.created_by, "someone");
+    assert_eq!(recovered.code, "abc");
+
+    //? Missing properties fall back to their defaults.
+    let mut props = HashMap::new();
+    props.insert(
+        String::from("createdBy"),
+        Value::StringValue(String::from("someone")),
+    );
+    props.insert(
+        String::from("note"),
+        Value::StringValue(String::from("hello")),
+    );
+    props.insert(
+        String::from("code"),
+        Value::StringValue(String::from("XYZ")),
+    );
+    let recovered = Foo::from_value(Value::EntityValue(props.clone())).unwrap();
+    assert_eq!(recovered.count, 0);
+    assert_eq!(recovered.label, "unlabeled");
+    assert_eq!(recovered.code, "xyz");
+
+    //? A `with` field without a default is still required.
+    props.remove("code");
+    assert!(Foo::from_value(Value::EntityValue(props)).is_err());
+}
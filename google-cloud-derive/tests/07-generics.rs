@@ -0,0 +1,49 @@
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Wrapper<T> {
+    inner: T,
+    items: Vec<T>,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct UserId(i64);
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Point(f64, f64);
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Marker;
+
+fn main() {
+    //? Generic structs.
+    let wrapper = Wrapper {
+        inner: String::from("test"),
+        items: vec![String::from("a"), String::from("b")],
+    };
+    let converted = wrapper.into_value();
+    let recovered = Wrapper::<String>::from_value(converted).unwrap();
+    assert_eq!(recovered.inner, "test");
+    assert_eq!(recovered.items, vec!["a", "b"]);
+
+    //? Newtype structs are transparent.
+    assert_eq!(UserId(42).into_value(), Value::IntegerValue(42));
+    assert_eq!(
+        UserId::from_value(Value::IntegerValue(42)).unwrap(),
+        UserId(42)
+    );
+
+    //? Tuple structs are stored as arrays.
+    let converted = Point(1.5, 2.5).into_value();
+    assert_eq!(
+        converted,
+        Value::ArrayValue(vec![Value::DoubleValue(1.5), Value::DoubleValue(2.5)])
+    );
+    assert_eq!(Point::from_value(converted).unwrap(), Point(1.5, 2.5));
+    assert!(Point::from_value(Value::ArrayValue(vec![Value::DoubleValue(1.5)])).is_err());
+
+    //? Unit structs are stored as empty entities.
+    let converted = Marker.into_value();
+    assert_eq!(converted, Value::EntityValue(Default::default()));
+    assert_eq!(Marker::from_value(converted).unwrap(), Marker);
+}
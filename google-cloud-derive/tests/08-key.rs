@@ -0,0 +1,30 @@
+use google_cloud::datastore::{FromEntity, FromValue, IntoEntity, IntoValue, Key, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Foo {
+    #[datastore(key)]
+    key: Key,
+    #[datastore(exclude_from_indexes)]
+    bar: String,
+    qux: bool,
+}
+
+fn main() {
+    let foo = Foo {
+        key: Key::new("foo").id(1),
+        bar: String::from("test"),
+        qux: true,
+    };
+
+    let entity = foo.into_entity().unwrap();
+    assert_eq!(entity.key(), &Key::new("foo").id(1));
+    assert!(entity.is_excluded_from_indexes("bar"));
+    match entity.properties() {
+        Value::EntityValue(props) => assert!(!props.contains_key("key")),
+        _ => panic!("expected an entity value"),
+    }
+
+    let recovered = Foo::from_entity(entity).unwrap();
+    assert_eq!(recovered.key, Key::new("foo").id(1));
+    assert_eq!(recovered.bar, "test");
+}
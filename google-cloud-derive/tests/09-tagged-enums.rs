@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+use google_cloud::error::ConvertError;
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub enum Unit {
+    Foo,
+    Bar,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+#[datastore(tag = "type")]
+pub enum Internal {
+    Empty,
+    Circle {
+        radius: f64,
+    },
+    #[datastore(rename = "rect")]
+    Rectangle {
+        width: f64,
+        #[datastore(exclude_from_indexes)]
+        height: f64,
+    },
+    Wrapped(Inner),
+    Number(i64),
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Inner {
+    label: String,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+#[datastore(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Adjacent {
+    Nothing,
+    Number(i64),
+    Pair(i64, String),
+    Named {
+        #[datastore(exclude_from_indexes)]
+        description: String,
+    },
+}
+
+fn string(value: &str) -> Value {
+    Value::StringValue(String::from(value))
+}
+
+fn main() {
+    //? Unknown variants are reported as errors.
+    assert_eq!(Unit::from_value(Unit::Bar.into_value()).unwrap(), Unit::Bar);
+    match Unit::from_value(string("Qux")) {
+        Err(ConvertError::UnknownVariant(variant)) => assert_eq!(variant, "Qux"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    //? Internally tagged enums store the tag along with the variant's properties.
+    let converted = Internal::Rectangle {
+        width: 1.0,
+        height: 2.0,
+    }
+    .into_value();
+    match &converted {
+        Value::EntityValue(props) => {
+            assert_eq!(props.get("type"), Some(&string("rect")));
+            assert_eq!(props.get("width"), Some(&Value::DoubleValue(1.0)));
+        }
+        _ => panic!("expected an entity value"),
+    }
+    assert_eq!(Internal::excluded_from_indexes(), vec!["height"]);
+
+    let values = vec![
+        Internal::Empty,
+        Internal::Circle { radius: 3.0 },
+        Internal::Rectangle {
+            width: 1.0,
+            height: 2.0,
+        },
+        Internal::Wrapped(Inner {
+            label: String::from("test"),
+        }),
+        Internal::Number(42),
+    ];
+    for value in values {
+        let converted = value.into_value();
+        let recovered = Internal::from_value(converted.clone()).unwrap();
+        assert_eq!(recovered.into_value(), converted);
+    }
+
+    //? Payloads which are not entities are stored under a dedicated property.
+    let converted = Internal::Number(42).into_value();
+    match &converted {
+        Value::EntityValue(props) => {
+            assert_eq!(props.get("type"), Some(&string("number")));
+            assert_eq!(props.get("$value"), Some(&Value::IntegerValue(42)));
+        }
+        _ => panic!("expected an entity value"),
+    }
+    assert_eq!(
+        Internal::from_value(converted).unwrap(),
+        Internal::Number(42)
+    );
+
+    let mut props = HashMap::new();
+    props.insert(String::from("type"), string("Triangle"));
+    match Internal::from_value(Value::EntityValue(props)) {
+        Err(ConvertError::UnknownVariant(variant)) => assert_eq!(variant, "Triangle"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(Internal::from_value(Value::EntityValue(HashMap::new())).is_err());
+
+    //? Adjacently tagged enums store the variant's data under the content property.
+    let converted = Adjacent::Pair(1, String::from("one")).into_value();
+    match &converted {
+        Value::EntityValue(props) => {
+            assert_eq!(props.get("kind"), Some(&string("pair")));
+            assert_eq!(
+                props.get("data"),
+                Some(&Value::ArrayValue(vec![
+                    Value::IntegerValue(1),
+                    string("one")
+                ]))
+            );
+        }
+        _ => panic!("expected an entity value"),
+    }
+    assert_eq!(Adjacent::excluded_from_indexes(), vec!["data.description"]);
+
+    let values = vec![
+        Adjacent::Nothing,
+        Adjacent::Number(42),
+        Adjacent::Pair(1, String::from("one")),
+        Adjacent::Named {
+            description: String::from("test"),
+        },
+    ];
+    for value in values {
+        let converted = value.into_value();
+        let recovered = Adjacent::from_value(converted.clone()).unwrap();
+        assert_eq!(recovered.into_value(), converted);
+    }
+}
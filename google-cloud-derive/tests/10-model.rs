@@ -0,0 +1,74 @@
+use google_cloud::datastore::{Filter, FromValue, IntoValue, Key, Model, Order, Value};
+
+#[derive(Debug, FromValue, IntoValue, Model)]
+#[datastore(
+    kind = "users",
+    namespace = "dev",
+    id = "String",
+    rename_all = "snake_case"
+)]
+pub struct User {
+    #[datastore(key)]
+    key: Key,
+    display_name: String,
+    #[datastore(rename = "years")]
+    age: i64,
+    address: Address,
+}
+
+#[derive(Debug, FromValue, IntoValue, Model)]
+pub struct Address {
+    city: String,
+}
+
+fn main() {
+    assert_eq!(User::KIND, "users");
+    assert_eq!(User::NAMESPACE, Some("dev"));
+    assert_eq!(
+        User::key("john"),
+        Key::new("users").id("john").namespace("dev")
+    );
+
+    //? The kind defaults to the name of the type, and IDs default to integers.
+    assert_eq!(Address::KIND, "Address");
+    assert_eq!(Address::NAMESPACE, None);
+    assert_eq!(Address::key(10), Key::new("Address").id(10));
+
+    //? Property paths follow renames.
+    assert_eq!(User::KEY.name(), "__key__");
+    assert_eq!(User::DISPLAY_NAME.name(), "display_name");
+    assert_eq!(User::AGE.name(), "years");
+    assert_eq!(User::ADDRESS.join(Address::CITY).name(), "address.city");
+
+    assert_eq!(
+        User::AGE.greater_than(18).into_filter(),
+        Filter::GreaterThan(String::from("years"), Value::IntegerValue(18))
+    );
+    assert_eq!(
+        User::ADDRESS
+            .join(Address::CITY)
+            .one_of(vec!["Paris", "Lyon"])
+            .into_filter(),
+        Filter::In(
+            String::from("address.city"),
+            vec!["Paris".into_value(), "Lyon".into_value()]
+        )
+    );
+    assert_eq!(
+        User::DISPLAY_NAME.desc().into_order(),
+        Order::Desc(String::from("display_name"))
+    );
+
+    //? Filters of the same model can be combined.
+    assert_eq!(
+        Filter::from(
+            User::AGE
+                .greater_than(18)
+                .and(User::DISPLAY_NAME.equal("john"))
+        ),
+        Filter::And(vec![
+            Filter::GreaterThan(String::from("years"), Value::IntegerValue(18)),
+            Filter::Equal(String::from("display_name"), "john".into_value()),
+        ])
+    );
+}
@@ -0,0 +1,57 @@
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Post {
+    title: String,
+    #[datastore(flatten)]
+    meta: Option<Meta>,
+    #[datastore(flatten)]
+    score: i64,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+pub struct Meta {
+    author: String,
+}
+
+fn main() {
+    //? Flattened entities are merged into the parent's properties.
+    let post = Post {
+        title: String::from("hello"),
+        meta: Some(Meta {
+            author: String::from("someone"),
+        }),
+        score: 3,
+    };
+    let converted = post.into_value();
+    let props = match &converted {
+        Value::EntityValue(props) => props,
+        _ => panic!("expected an entity value"),
+    };
+    assert!(!props.contains_key("meta"));
+    assert_eq!(
+        props.get("author"),
+        Some(&Value::StringValue(String::from("someone")))
+    );
+    //? Flattened values which are not entities are kept under their field's name.
+    assert_eq!(props.get("score"), Some(&Value::IntegerValue(3)));
+    let recovered = Post::from_value(converted).unwrap();
+    assert_eq!(recovered.meta.unwrap().author, "someone");
+    assert_eq!(recovered.score, 3);
+
+    //? A missing flattened entity round-trips as `None`.
+    let post = Post {
+        title: String::from("hello"),
+        meta: None,
+        score: 0,
+    };
+    let converted = post.into_value();
+    let props = match &converted {
+        Value::EntityValue(props) => props,
+        _ => panic!("expected an entity value"),
+    };
+    assert_eq!(props.get("meta"), Some(&Value::NullValue));
+    let recovered = Post::from_value(converted).unwrap();
+    assert_eq!(recovered.meta, None);
+    assert_eq!(recovered.score, 0);
+}
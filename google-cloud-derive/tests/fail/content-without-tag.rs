@@ -0,0 +1,9 @@
+use google_cloud::datastore::{FromValue, IntoValue};
+
+#[derive(FromValue, IntoValue)]
+#[datastore(content = "data")]
+pub enum Shape {
+    Circle { radius: f64 },
+}
+
+fn main() {}
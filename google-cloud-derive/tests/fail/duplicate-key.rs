@@ -0,0 +1,12 @@
+use google_cloud::datastore::{FromValue, IntoValue, Key};
+
+#[derive(FromValue, IntoValue)]
+pub struct User {
+    #[datastore(key)]
+    key: Key,
+    #[datastore(key)]
+    other: Key,
+    name: String,
+}
+
+fn main() {}
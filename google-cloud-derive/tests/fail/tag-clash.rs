@@ -0,0 +1,13 @@
+use google_cloud::datastore::{FromValue, IntoValue};
+
+#[derive(FromValue, IntoValue)]
+#[datastore(tag = "type")]
+pub enum Shape {
+    Circle {
+        radius: f64,
+        #[datastore(rename = "type")]
+        kind: String,
+    },
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use google_cloud::datastore::{FromValue, IntoValue, Key};
+
+#[derive(FromValue, IntoValue)]
+pub struct User(#[datastore(key)] Key, String);
+
+fn main() {}
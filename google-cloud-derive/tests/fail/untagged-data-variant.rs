@@ -0,0 +1,9 @@
+use google_cloud::datastore::{FromValue, IntoValue};
+
+#[derive(FromValue, IntoValue)]
+pub enum Shape {
+    Empty,
+    Circle { radius: f64 },
+}
+
+fn main() {}
@@ -6,4 +6,17 @@ fn tests() {
     tests.pass("tests/01-simple.rs");
     tests.pass("tests/02-nested.rs");
     tests.pass("tests/03-enums.rs");
+    tests.pass("tests/04-optional.rs");
+    tests.pass("tests/05-indexes.rs");
+    tests.pass("tests/06-attributes.rs");
+    tests.pass("tests/07-generics.rs");
+    tests.pass("tests/08-key.rs");
+    tests.pass("tests/09-tagged-enums.rs");
+    tests.pass("tests/10-model.rs");
+    tests.pass("tests/11-flatten.rs");
+    tests.compile_fail("tests/fail/duplicate-key.rs");
+    tests.compile_fail("tests/fail/tuple-key.rs");
+    tests.compile_fail("tests/fail/content-without-tag.rs");
+    tests.compile_fail("tests/fail/untagged-data-variant.rs");
+    tests.compile_fail("tests/fail/tag-clash.rs");
 }
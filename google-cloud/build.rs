@@ -1,28 +1,44 @@
 use std::fs;
+use std::io;
+use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let protos = [
-        (["protos/google/pubsub/v1/pubsub.proto"], "src/pubsub/api"),
+        (
+            ["protos/google/pubsub/v1/pubsub.proto"],
+            "src/pubsub/api",
+            false,
+        ),
         (
             ["protos/google/datastore/v1/datastore.proto"],
             "src/datastore/api",
+            // The server is used by the in-memory fake of `datastore::testing`.
+            true,
         ),
         (
             ["protos/google/cloud/vision/v1/image_annotator.proto"],
             "src/vision/api",
+            false,
         ),
     ];
 
-    for (proto_files, out_dir) in protos.iter() {
-        fs::create_dir_all(&out_dir)?;
+    for (proto_files, out_dir, build_server) in protos.iter() {
+        fs::create_dir_all(out_dir)?;
 
         tonic_build::configure()
             .build_client(true)
-            .build_server(false)
+            .build_server(*build_server)
             .format(true)
-            .out_dir(&out_dir)
+            .out_dir(out_dir)
             .compile(proto_files, &["protos"])?;
 
+        for entry in fs::read_dir(out_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                neutralize_code_blocks(&path)?;
+            }
+        }
+
         for file in proto_files {
             println!("cargo:rerun-if-changed={}", &file);
         }
@@ -30,3 +46,35 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Marks the fenced blocks of the generated doc comments as text.
+///
+/// The proto comments contain examples written in other languages (like GQL), which would
+/// otherwise be compiled and run as Rust doctests.
+fn neutralize_code_blocks(path: &Path) -> io::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut in_block = false;
+    let lines: Vec<_> = source
+        .lines()
+        .map(|line| {
+            let fence = line.trim_start();
+            if !fence.starts_with("/// ```") {
+                return line.to_string();
+            }
+            let opens_untagged = !in_block && fence == "/// ```";
+            in_block = !in_block;
+            if opens_untagged {
+                format!("{}text", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    if output != source {
+        fs::write(path, output)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::datastore::api;
+use crate::datastore::{Query, Value};
+use crate::error::ConvertError;
+
+/// Represents a Datastore aggregation function.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregation {
+    /// Counts the entities matching the query, optionally stopping at an upper bound.
+    Count {
+        /// The name under which the result is returned.
+        alias: String,
+        /// The maximum number of entities to count.
+        up_to: Option<i64>,
+    },
+    /// Sums the numeric values of a property.
+    Sum {
+        /// The name under which the result is returned.
+        alias: String,
+        /// The property to aggregate on.
+        property: String,
+    },
+    /// Averages the numeric values of a property.
+    Avg {
+        /// The name under which the result is returned.
+        alias: String,
+        /// The property to aggregate on.
+        property: String,
+    },
+}
+
+/// Represents a Datastore aggregation query, running aggregation functions over a query.
+///
+/// ```
+/// # use google_cloud::datastore::{AggregationQuery, Query};
+/// let query = AggregationQuery::new(Query::new("users"))
+///     .count("total")
+///     .sum("total_age", "age")
+///     .avg("average_age", "age");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregationQuery {
+    pub(crate) query: Query,
+    pub(crate) aggregations: Vec<Aggregation>,
+}
+
+impl AggregationQuery {
+    /// Construct a new aggregation query, without any aggregation functions yet.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{AggregationQuery, Query};
+    /// let query = AggregationQuery::new(Query::new("users"));
+    /// ```
+    pub fn new(query: Query) -> AggregationQuery {
+        AggregationQuery {
+            query,
+            aggregations: Vec::new(),
+        }
+    }
+
+    /// Count the entities matching the query.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{AggregationQuery, Query};
+    /// let query = AggregationQuery::new(Query::new("users")).count("total");
+    /// ```
+    pub fn count(mut self, alias: impl Into<String>) -> AggregationQuery {
+        self.aggregations.push(Aggregation::Count {
+            alias: alias.into(),
+            up_to: None,
+        });
+        self
+    }
+
+    /// Count the entities matching the query, without scanning more than `up_to` entities.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{AggregationQuery, Query};
+    /// let query = AggregationQuery::new(Query::new("users")).count_up_to("total", 1000);
+    /// ```
+    pub fn count_up_to(mut self, alias: impl Into<String>, up_to: i64) -> AggregationQuery {
+        self.aggregations.push(Aggregation::Count {
+            alias: alias.into(),
+            up_to: Some(up_to),
+        });
+        self
+    }
+
+    /// Sum the numeric values of a property, across the entities matching the query.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{AggregationQuery, Query};
+    /// let query = AggregationQuery::new(Query::new("users")).sum("total_age", "age");
+    /// ```
+    pub fn sum(
+        mut self,
+        alias: impl Into<String>,
+        property: impl Into<String>,
+    ) -> AggregationQuery {
+        self.aggregations.push(Aggregation::Sum {
+            alias: alias.into(),
+            property: property.into(),
+        });
+        self
+    }
+
+    /// Average the numeric values of a property, across the entities matching the query.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{AggregationQuery, Query};
+    /// let query = AggregationQuery::new(Query::new("users")).avg("average_age", "age");
+    /// ```
+    pub fn avg(
+        mut self,
+        alias: impl Into<String>,
+        property: impl Into<String>,
+    ) -> AggregationQuery {
+        self.aggregations.push(Aggregation::Avg {
+            alias: alias.into(),
+            property: property.into(),
+        });
+        self
+    }
+
+    /// Add any aggregation function to the query.
+    pub fn aggregation(mut self, aggregation: Aggregation) -> AggregationQuery {
+        self.aggregations.push(aggregation);
+        self
+    }
+}
+
+impl From<Query> for AggregationQuery {
+    fn from(query: Query) -> AggregationQuery {
+        AggregationQuery::new(query)
+    }
+}
+
+/// Represents the results of an aggregation query, indexed by their aliases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregationResult {
+    pub(crate) properties: HashMap<String, Value>,
+}
+
+impl AggregationResult {
+    /// Get the result of an aggregation function, by its alias.
+    ///
+    /// Averages over no numeric values are null.
+    pub fn get(&self, alias: &str) -> Option<&Value> {
+        self.properties.get(alias)
+    }
+
+    /// Get the result of a count aggregation, by its alias.
+    pub fn count(&self, alias: &str) -> Option<i64> {
+        match self.properties.get(alias) {
+            Some(Value::IntegerValue(count)) => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// Get the result of a sum or average aggregation as a floating-point number, by its alias.
+    pub fn number(&self, alias: &str) -> Option<f64> {
+        match self.properties.get(alias) {
+            Some(Value::IntegerValue(value)) => Some(*value as f64),
+            Some(Value::DoubleValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the results of all the aggregation functions, indexed by their aliases.
+    pub fn properties(&self) -> &HashMap<String, Value> {
+        &self.properties
+    }
+
+    /// Get the results of all the aggregation functions, indexed by their aliases.
+    pub fn into_properties(self) -> HashMap<String, Value> {
+        self.properties
+    }
+}
+
+impl TryFrom<api::AggregationResult> for AggregationResult {
+    type Error = ConvertError;
+
+    fn try_from(result: api::AggregationResult) -> Result<AggregationResult, ConvertError> {
+        let properties = result
+            .aggregate_properties
+            .into_iter()
+            .map(|(alias, value)| match value.value_type {
+                Some(value_type) => Ok((alias, Value::from(value_type))),
+                None => Err(ConvertError::MissingProperty(alias)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(AggregationResult { properties })
+    }
+}
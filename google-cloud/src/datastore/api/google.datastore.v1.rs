@@ -285,6 +285,135 @@ pub struct Query {
     #[prost(message, optional, tag = "12")]
     pub limit: ::core::option::Option<i32>,
 }
+/// Datastore query for running an aggregation over a
+/// [Query][google.datastore.v1.Query].
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregationQuery {
+    /// Optional. Series of aggregations to apply over the results of the
+    /// `nested_query`.
+    ///
+    /// Requires:
+    ///
+    /// * A minimum of one and maximum of five aggregations per query.
+    #[prost(message, repeated, tag = "3")]
+    pub aggregations: ::prost::alloc::vec::Vec<aggregation_query::Aggregation>,
+    /// The base query to aggregate over.
+    #[prost(oneof = "aggregation_query::QueryType", tags = "1")]
+    pub query_type: ::core::option::Option<aggregation_query::QueryType>,
+}
+/// Nested message and enum types in `AggregationQuery`.
+pub mod aggregation_query {
+    /// Defines an aggregation that produces a single result.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Aggregation {
+        /// Optional. Optional name of the property to store the result of the
+        /// aggregation.
+        ///
+        /// If not provided, Datastore will pick a default name following the format
+        /// `property_<incremental_id++>`.
+        ///
+        /// Requires:
+        ///
+        /// * Must be unique across all aggregation aliases.
+        /// * Conform to [entity property
+        /// name][google.datastore.v1.Entity.properties] limitations.
+        #[prost(string, tag = "7")]
+        pub alias: ::prost::alloc::string::String,
+        /// The type of aggregation to perform, required.
+        #[prost(oneof = "aggregation::Operator", tags = "1, 2, 3")]
+        pub operator: ::core::option::Option<aggregation::Operator>,
+    }
+    /// Nested message and enum types in `Aggregation`.
+    pub mod aggregation {
+        /// Count of entities that match the query.
+        ///
+        /// The `COUNT(*)` aggregation function operates on the entire entity
+        /// so it does not require a field reference.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Count {
+            /// Optional. Optional constraint on the maximum number of entities to
+            /// count.
+            ///
+            /// This provides a way to set an upper bound on the number of entities
+            /// to scan, limiting latency, and cost.
+            ///
+            /// Unspecified is interpreted as no bound.
+            ///
+            /// If a zero value is provided, a count result of zero should always be
+            /// expected.
+            ///
+            /// High-Level Example:
+            ///
+            /// ```text
+            /// AGGREGATE COUNT_UP_TO(1000) OVER ( SELECT * FROM k );
+            /// ```
+            ///
+            /// Requires:
+            ///
+            /// * Must be non-negative when present.
+            #[prost(message, optional, tag = "1")]
+            pub up_to: ::core::option::Option<i64>,
+        }
+        /// Sum of the values of the requested property.
+        ///
+        /// * Only numeric values will be aggregated. All non-numeric values
+        /// including `NULL` are skipped.
+        ///
+        /// * If the aggregated values contain `NaN`, returns `NaN`. Infinity math
+        /// follows IEEE-754 standards.
+        ///
+        /// * If the aggregated value set is empty, returns 0.
+        ///
+        /// * Returns a 64-bit integer if all aggregated numbers are integers and the
+        /// sum result does not overflow. Otherwise, the result is returned as a
+        /// double. Note that even if all the aggregated values are integers, the
+        /// result is returned as a double if it cannot fit within a 64-bit signed
+        /// integer. When this occurs, the returned value will lose precision.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Sum {
+            /// The property to aggregate on.
+            #[prost(message, optional, tag = "1")]
+            pub property: ::core::option::Option<super::super::PropertyReference>,
+        }
+        /// Average of the values of the requested property.
+        ///
+        /// * Only numeric values will be aggregated. All non-numeric values
+        /// including `NULL` are skipped.
+        ///
+        /// * If the aggregated values contain `NaN`, returns `NaN`. Infinity math
+        /// follows IEEE-754 standards.
+        ///
+        /// * If the aggregated value set is empty, returns `NULL`.
+        ///
+        /// * Always returns the result as a double.
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Avg {
+            /// The property to aggregate on.
+            #[prost(message, optional, tag = "1")]
+            pub property: ::core::option::Option<super::super::PropertyReference>,
+        }
+        /// The type of aggregation to perform, required.
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum Operator {
+            /// Count aggregator.
+            #[prost(message, tag = "1")]
+            Count(Count),
+            /// Sum aggregator.
+            #[prost(message, tag = "2")]
+            Sum(Sum),
+            /// Average aggregator.
+            #[prost(message, tag = "3")]
+            Avg(Avg),
+        }
+    }
+    /// The base query to aggregate over.
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum QueryType {
+        /// Nested query for aggregation
+        #[prost(message, tag = "1")]
+        NestedQuery(super::Query),
+    }
+}
 /// A representation of a kind.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KindExpression {
@@ -372,6 +501,8 @@ pub mod composite_filter {
         Unspecified = 0,
         /// The results are required to satisfy each of the combined filters.
         And = 1,
+        /// Documents are required to satisfy at least one of the combined filters.
+        Or = 2,
     }
 }
 /// A filter on a specific property.
@@ -395,18 +526,62 @@ pub mod property_filter {
     pub enum Operator {
         /// Unspecified. This value must not be used.
         Unspecified = 0,
-        /// Less than.
+        /// The given `property` is less than the given `value`.
+        ///
+        /// Requires:
+        ///
+        /// * That `property` comes first in `order_by`.
         LessThan = 1,
-        /// Less than or equal.
+        /// The given `property` is less than or equal to the given `value`.
+        ///
+        /// Requires:
+        ///
+        /// * That `property` comes first in `order_by`.
         LessThanOrEqual = 2,
-        /// Greater than.
+        /// The given `property` is greater than the given `value`.
+        ///
+        /// Requires:
+        ///
+        /// * That `property` comes first in `order_by`.
         GreaterThan = 3,
-        /// Greater than or equal.
+        /// The given `property` is greater than or equal to the given `value`.
+        ///
+        /// Requires:
+        ///
+        /// * That `property` comes first in `order_by`.
         GreaterThanOrEqual = 4,
-        /// Equal.
+        /// The given `property` is equal to the given `value`.
         Equal = 5,
-        /// Has ancestor.
+        /// The given `property` is equal to at least one value in the given array.
+        ///
+        /// Requires:
+        ///
+        /// * That `value` is a non-empty `ArrayValue`, subject to disjunction
+        ///   limits.
+        /// * No `NOT_IN` is in the same query.
+        In = 6,
+        /// The given `property` is not equal to the given `value`.
+        ///
+        /// Requires:
+        ///
+        /// * No other `NOT_EQUAL` or `NOT_IN` is in the same query.
+        /// * That `property` comes first in the `order_by`.
+        NotEqual = 9,
+        /// Limit the result set to the given entity and its descendants.
+        ///
+        /// Requires:
+        ///
+        /// * That `value` is an entity key.
+        /// * All evaluated disjunctions must have the same `HAS_ANCESTOR` filter.
         HasAncestor = 11,
+        /// The value of the `property` is not in the given array.
+        ///
+        /// Requires:
+        ///
+        /// * That `value` is a non-empty `ArrayValue` with at most 10 values.
+        /// * No other `OR`, `IN`, `NOT_IN`, `NOT_EQUAL` is in the same query.
+        /// * That `field` comes first in the `order_by`.
+        NotIn = 13,
     }
 }
 /// A [GQL
@@ -513,6 +688,34 @@ pub mod query_result_batch {
         NoMoreResults = 3,
     }
 }
+/// The result of a single bucket from a Datastore aggregation query.
+///
+/// The keys of `aggregate_properties` are the same for all results in an
+/// aggregation query, unlike entity queries which can have different fields
+/// present for each result.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregationResult {
+    /// The result of the aggregation functions, ex: `COUNT(*) AS total_entities`.
+    ///
+    /// The key is the
+    /// [alias][google.datastore.v1.AggregationQuery.Aggregation.alias] assigned to
+    /// the aggregation function on input and the size of this map equals the
+    /// number of aggregation functions in the query.
+    #[prost(map = "string, message", tag = "2")]
+    pub aggregate_properties: ::std::collections::HashMap<::prost::alloc::string::String, Value>,
+}
+/// A batch of aggregation results produced by an aggregation query.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregationResultBatch {
+    /// The aggregation results for this batch.
+    #[prost(message, repeated, tag = "1")]
+    pub aggregation_results: ::prost::alloc::vec::Vec<AggregationResult>,
+    /// The state of the query after the current batch.
+    /// Only COUNT(*) aggregations are supported in the initial launch. Therefore,
+    /// expected result type is limited to `NO_MORE_RESULTS`.
+    #[prost(enumeration = "query_result_batch::MoreResultsType", tag = "2")]
+    pub more_results: i32,
+}
 /// The request for [Datastore.Lookup][google.datastore.v1.Datastore.Lookup].
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct LookupRequest {
@@ -589,6 +792,50 @@ pub struct RunQueryResponse {
     pub query: ::core::option::Option<Query>,
 }
 /// The request for
+/// [Datastore.RunAggregationQuery][google.datastore.v1.Datastore.RunAggregationQuery].
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RunAggregationQueryRequest {
+    /// The ID of the project against which to make the request.
+    #[prost(string, tag = "8")]
+    pub project_id: ::prost::alloc::string::String,
+    /// Entities are partitioned into subsets, identified by a partition ID.
+    /// Queries are scoped to a single partition.
+    /// This partition ID is normalized with the standard default context
+    /// partition ID.
+    #[prost(message, optional, tag = "2")]
+    pub partition_id: ::core::option::Option<PartitionId>,
+    /// The options for this query.
+    #[prost(message, optional, tag = "1")]
+    pub read_options: ::core::option::Option<ReadOptions>,
+    /// The type of query.
+    #[prost(oneof = "run_aggregation_query_request::QueryType", tags = "3, 7")]
+    pub query_type: ::core::option::Option<run_aggregation_query_request::QueryType>,
+}
+/// Nested message and enum types in `RunAggregationQueryRequest`.
+pub mod run_aggregation_query_request {
+    /// The type of query.
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum QueryType {
+        /// The query to run.
+        #[prost(message, tag = "3")]
+        AggregationQuery(super::AggregationQuery),
+        /// The GQL query to run. This query must be an aggregation query.
+        #[prost(message, tag = "7")]
+        GqlQuery(super::GqlQuery),
+    }
+}
+/// The response for
+/// [Datastore.RunAggregationQuery][google.datastore.v1.Datastore.RunAggregationQuery].
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RunAggregationQueryResponse {
+    /// A batch of aggregation results. Always present.
+    #[prost(message, optional, tag = "1")]
+    pub batch: ::core::option::Option<AggregationResultBatch>,
+    /// The parsed form of the `GqlQuery` from the request, if it was set.
+    #[prost(message, optional, tag = "2")]
+    pub query: ::core::option::Option<AggregationQuery>,
+}
+/// The request for
 /// [Datastore.BeginTransaction][google.datastore.v1.Datastore.BeginTransaction].
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BeginTransactionRequest {
@@ -812,7 +1059,7 @@ pub struct ReadOptions {
     /// If not specified, lookups and ancestor queries default to
     /// `read_consistency`=`STRONG`, global queries default to
     /// `read_consistency`=`EVENTUAL`.
-    #[prost(oneof = "read_options::ConsistencyType", tags = "1, 2")]
+    #[prost(oneof = "read_options::ConsistencyType", tags = "1, 2, 4")]
     pub consistency_type: ::core::option::Option<read_options::ConsistencyType>,
 }
 /// Nested message and enum types in `ReadOptions`.
@@ -842,6 +1089,11 @@ pub mod read_options {
         /// [Datastore.BeginTransaction][google.datastore.v1.Datastore.BeginTransaction].
         #[prost(bytes, tag = "2")]
         Transaction(::prost::alloc::vec::Vec<u8>),
+        /// Reads entities as they were at the given time. This may not be older
+        /// than 270 seconds.  This value is only supported for Cloud Firestore in
+        /// Datastore mode.
+        #[prost(message, tag = "4")]
+        ReadTime(::prost_types::Timestamp),
     }
 }
 /// Options for beginning a new transaction.
@@ -954,6 +1206,23 @@ pub mod datastore_client {
                 http::uri::PathAndQuery::from_static("/google.datastore.v1.Datastore/RunQuery");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        #[doc = " Runs an aggregation query."]
+        pub async fn run_aggregation_query(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RunAggregationQueryRequest>,
+        ) -> Result<tonic::Response<super::RunAggregationQueryResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/google.datastore.v1.Datastore/RunAggregationQuery",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         #[doc = " Begins a new transaction."]
         pub async fn begin_transaction(
             &mut self,
@@ -1052,3 +1321,377 @@ pub mod datastore_client {
         }
     }
 }
+#[doc = r" Generated server implementations."]
+pub mod datastore_server {
+    #![allow(unused_variables, dead_code, missing_docs)]
+    use tonic::codegen::*;
+    #[doc = "Generated trait containing gRPC methods that should be implemented for use with DatastoreServer."]
+    #[async_trait]
+    pub trait Datastore: Send + Sync + 'static {
+        #[doc = " Looks up entities by key."]
+        async fn lookup(
+            &self,
+            request: tonic::Request<super::LookupRequest>,
+        ) -> Result<tonic::Response<super::LookupResponse>, tonic::Status>;
+        #[doc = " Queries for entities."]
+        async fn run_query(
+            &self,
+            request: tonic::Request<super::RunQueryRequest>,
+        ) -> Result<tonic::Response<super::RunQueryResponse>, tonic::Status>;
+        #[doc = " Runs an aggregation query."]
+        async fn run_aggregation_query(
+            &self,
+            request: tonic::Request<super::RunAggregationQueryRequest>,
+        ) -> Result<tonic::Response<super::RunAggregationQueryResponse>, tonic::Status>;
+        #[doc = " Begins a new transaction."]
+        async fn begin_transaction(
+            &self,
+            request: tonic::Request<super::BeginTransactionRequest>,
+        ) -> Result<tonic::Response<super::BeginTransactionResponse>, tonic::Status>;
+        #[doc = " Commits a transaction, optionally creating, deleting or modifying some"]
+        #[doc = " entities."]
+        async fn commit(
+            &self,
+            request: tonic::Request<super::CommitRequest>,
+        ) -> Result<tonic::Response<super::CommitResponse>, tonic::Status>;
+        #[doc = " Rolls back a transaction."]
+        async fn rollback(
+            &self,
+            request: tonic::Request<super::RollbackRequest>,
+        ) -> Result<tonic::Response<super::RollbackResponse>, tonic::Status>;
+        #[doc = " Allocates IDs for the given keys, which is useful for referencing an entity"]
+        #[doc = " before it is inserted."]
+        async fn allocate_ids(
+            &self,
+            request: tonic::Request<super::AllocateIdsRequest>,
+        ) -> Result<tonic::Response<super::AllocateIdsResponse>, tonic::Status>;
+        #[doc = " Prevents the supplied keys' IDs from being auto-allocated by Cloud"]
+        #[doc = " Datastore."]
+        async fn reserve_ids(
+            &self,
+            request: tonic::Request<super::ReserveIdsRequest>,
+        ) -> Result<tonic::Response<super::ReserveIdsResponse>, tonic::Status>;
+    }
+    #[doc = " Each RPC normalizes the partition IDs of the keys in its input entities,"]
+    #[doc = " and always returns entities with keys with normalized partition IDs."]
+    #[doc = " This applies to all keys and entities, including those in values, except keys"]
+    #[doc = " with both an empty path and an empty or unset partition ID. Normalization of"]
+    #[doc = " input keys sets the project ID (if not already set) to the project ID from"]
+    #[doc = " the request."]
+    #[doc = ""]
+    #[derive(Debug)]
+    pub struct DatastoreServer<T: Datastore> {
+        inner: _Inner<T>,
+    }
+    struct _Inner<T>(Arc<T>, Option<tonic::Interceptor>);
+    impl<T: Datastore> DatastoreServer<T> {
+        pub fn new(inner: T) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, None);
+            Self { inner }
+        }
+        pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, Some(interceptor.into()));
+            Self { inner }
+        }
+    }
+    impl<T, B> Service<http::Request<B>> for DatastoreServer<T>
+    where
+        T: Datastore,
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = Never;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/google.datastore.v1.Datastore/Lookup" => {
+                    #[allow(non_camel_case_types)]
+                    struct LookupSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::LookupRequest> for LookupSvc<T> {
+                        type Response = super::LookupResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LookupRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).lookup(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = LookupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/RunQuery" => {
+                    #[allow(non_camel_case_types)]
+                    struct RunQuerySvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::RunQueryRequest> for RunQuerySvc<T> {
+                        type Response = super::RunQueryResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RunQueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).run_query(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = RunQuerySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/RunAggregationQuery" => {
+                    #[allow(non_camel_case_types)]
+                    struct RunAggregationQuerySvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore>
+                        tonic::server::UnaryService<super::RunAggregationQueryRequest>
+                        for RunAggregationQuerySvc<T>
+                    {
+                        type Response = super::RunAggregationQueryResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RunAggregationQueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).run_aggregation_query(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = RunAggregationQuerySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/BeginTransaction" => {
+                    #[allow(non_camel_case_types)]
+                    struct BeginTransactionSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::BeginTransactionRequest>
+                        for BeginTransactionSvc<T>
+                    {
+                        type Response = super::BeginTransactionResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BeginTransactionRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).begin_transaction(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = BeginTransactionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/Commit" => {
+                    #[allow(non_camel_case_types)]
+                    struct CommitSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::CommitRequest> for CommitSvc<T> {
+                        type Response = super::CommitResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CommitRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).commit(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = CommitSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/Rollback" => {
+                    #[allow(non_camel_case_types)]
+                    struct RollbackSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::RollbackRequest> for RollbackSvc<T> {
+                        type Response = super::RollbackResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RollbackRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).rollback(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = RollbackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/AllocateIds" => {
+                    #[allow(non_camel_case_types)]
+                    struct AllocateIdsSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::AllocateIdsRequest> for AllocateIdsSvc<T> {
+                        type Response = super::AllocateIdsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AllocateIdsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).allocate_ids(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = AllocateIdsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/google.datastore.v1.Datastore/ReserveIds" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReserveIdsSvc<T: Datastore>(pub Arc<T>);
+                    impl<T: Datastore> tonic::server::UnaryService<super::ReserveIdsRequest> for ReserveIdsSvc<T> {
+                        type Response = super::ReserveIdsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReserveIdsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).reserve_ids(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = ReserveIdsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(tonic::body::BoxBody::empty())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: Datastore> Clone for DatastoreServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self { inner }
+        }
+    }
+    impl<T: Datastore> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone(), self.1.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Datastore> tonic::transport::NamedService for DatastoreServer<T> {
+        const NAME: &'static str = "google.datastore.v1.Datastore";
+    }
+}
@@ -0,0 +1,308 @@
+use std::borrow::Borrow;
+use std::iter;
+
+use futures::stream::{self, StreamExt};
+use prost::Message;
+
+use crate::datastore::api;
+use crate::datastore::client::convert_mutation;
+use crate::datastore::{Client, Entity, Error, IntoEntity, Key, Mutation, MutationResult};
+use crate::error::ConvertError;
+
+/// Writes large amounts of mutations to Datastore, by splitting them into multiple concurrent commits.
+///
+/// Mutations are split into chunks, limited both by their count and by their encoded size,
+/// and the chunks are committed with a bounded concurrency.
+/// A failing chunk does not prevent the others from being committed.
+///
+/// Just like [`Client::commit`], mutations are not applied atomically,
+/// and no two mutations may affect the same entity.
+///
+/// ```no_run
+/// # use google_cloud::datastore::{Client, Error, Key, Mutation};
+/// # async fn run(client: Client) -> Result<(), Error> {
+/// let writer = client.bulk_writer().concurrency(16);
+/// let keys = (1..=10_000).map(|id| Key::new("users").id(id));
+/// let result = writer.delete_all(keys).await;
+/// for failure in result.failures() {
+///     eprintln!("failed to delete {} entities: {}", failure.mutations().len(), failure.error());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BulkWriter {
+    pub(crate) client: Client,
+    pub(crate) max_mutations: usize,
+    pub(crate) max_bytes: usize,
+    pub(crate) concurrency: usize,
+}
+
+impl BulkWriter {
+    /// The maximum number of mutations Datastore accepts within a single commit.
+    pub const MAX_MUTATIONS: usize = 500;
+    /// The default limit on the encoded size of the mutations within a single commit,
+    /// leaving some headroom below the 10 MiB limit of Datastore requests.
+    pub const DEFAULT_MAX_BYTES: usize = 9 * 1024 * 1024;
+    /// The default number of commits sent concurrently.
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+
+    /// Creates a new bulk writer, with the default limits.
+    pub fn new(client: Client) -> BulkWriter {
+        BulkWriter {
+            client,
+            max_mutations: BulkWriter::MAX_MUTATIONS,
+            max_bytes: BulkWriter::DEFAULT_MAX_BYTES,
+            concurrency: BulkWriter::DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Limit the number of mutations within a single commit (capped to 500).
+    pub fn max_mutations(mut self, max_mutations: usize) -> BulkWriter {
+        self.max_mutations = max_mutations.clamp(1, BulkWriter::MAX_MUTATIONS);
+        self
+    }
+
+    /// Limit the encoded size of the mutations within a single commit.
+    ///
+    /// A single mutation larger than this limit is still sent, on its own.
+    pub fn max_bytes(mut self, max_bytes: usize) -> BulkWriter {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Limit the number of commits sent concurrently.
+    pub fn concurrency(mut self, concurrency: usize) -> BulkWriter {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Inserts or updates multiple entities.
+    ///
+    /// Just like [`Client::put_all`], entities with incomplete keys get inserted with a newly allocated key.
+    pub async fn put_all<T, I>(&self, entities: I) -> Result<BulkWriteResult, ConvertError>
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoEntity,
+    {
+        self.put_all_to(self.client.project_name.as_str(), entities)
+            .await
+    }
+
+    /// Inserts or updates multiple entities, into the given project.
+    pub(crate) async fn put_all_to<T, I>(
+        &self,
+        project_name: &str,
+        entities: I,
+    ) -> Result<BulkWriteResult, ConvertError>
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoEntity,
+    {
+        let mutations = entities
+            .into_iter()
+            .map(|entity| {
+                let entity = entity.into_entity()?;
+                if entity.key.is_incomplete() {
+                    Ok(Mutation::Insert(entity))
+                } else {
+                    Ok(Mutation::Upsert(entity))
+                }
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+
+        Ok(self.commit_to(project_name, mutations).await)
+    }
+
+    /// Deletes multiple entities identified by multiple keys.
+    pub async fn delete_all<T, I>(&self, keys: I) -> BulkWriteResult
+    where
+        I: IntoIterator<Item = T>,
+        T: Borrow<Key>,
+    {
+        let mutations = keys
+            .into_iter()
+            .map(|key| Mutation::Delete(key.borrow().clone()));
+        self.commit(mutations).await
+    }
+
+    /// Applies multiple mutations, split into multiple concurrent commits.
+    pub async fn commit<I>(&self, mutations: I) -> BulkWriteResult
+    where
+        I: IntoIterator<Item = Mutation>,
+    {
+        self.commit_to(self.client.project_name.as_str(), mutations)
+            .await
+    }
+
+    /// Applies multiple mutations to the given project, split into multiple concurrent commits.
+    pub(crate) async fn commit_to<I>(&self, project_name: &str, mutations: I) -> BulkWriteResult
+    where
+        I: IntoIterator<Item = Mutation>,
+    {
+        let max_mutations = self.max_mutations;
+        let max_bytes = self.max_bytes;
+
+        let mut mutations = mutations
+            .into_iter()
+            .map(|mutation| convert_mutation(project_name, mutation))
+            .peekable();
+        let chunks = iter::from_fn(move || {
+            let mut chunk = Vec::new();
+            let mut size = 0;
+            while let Some(mutation) = mutations.peek() {
+                let len = mutation.encoded_len();
+                if !chunk.is_empty() && (chunk.len() >= max_mutations || size + len > max_bytes) {
+                    break;
+                }
+                size += len;
+                chunk.extend(mutations.next());
+            }
+            Some(chunk).filter(|chunk| !chunk.is_empty())
+        });
+
+        let outcomes: Vec<_> = stream::iter(chunks)
+            .map(|chunk| {
+                let mut client = self.client.clone();
+                async move {
+                    //? The chunk is only kept while being committed, to hand its mutations back
+                    //? if the commit fails.
+                    let outcome = client
+                        .commit_mutations_to(project_name.to_string(), chunk.clone(), None)
+                        .await;
+                    match outcome {
+                        Ok(results) => {
+                            let written = chunk.iter().zip(results).map(|(mutation, res)| {
+                                let res = MutationResult::from(res);
+                                let key = res.key().cloned();
+                                let key = key.unwrap_or_else(|| mutation_key(mutation));
+                                (key, res)
+                            });
+                            Ok(written.collect::<Vec<_>>())
+                        }
+                        Err(error) => {
+                            let mutations = chunk.into_iter().map(restore_mutation).collect();
+                            Err(BulkWriteFailure { mutations, error })
+                        }
+                    }
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut result = BulkWriteResult {
+            written: Vec::new(),
+            failures: Vec::new(),
+        };
+        for outcome in outcomes {
+            match outcome {
+                Ok(written) => result.written.extend(written),
+                Err(failure) => result.failures.push(failure),
+            }
+        }
+
+        result
+    }
+}
+
+/// Represents the outcome of a bulk write.
+///
+/// Commits are independent from each other, so some of the mutations may have been applied
+/// even though others have failed.
+#[derive(Debug)]
+pub struct BulkWriteResult {
+    pub(crate) written: Vec<(Key, MutationResult)>,
+    pub(crate) failures: Vec<BulkWriteFailure>,
+}
+
+impl BulkWriteResult {
+    /// Whether all the mutations have been successfully applied.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Get the keys of the entities affected by the applied mutations, in the order of the mutations.
+    ///
+    /// Incomplete keys are replaced by the keys allocated by Datastore.
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.written.iter().map(|(key, _)| key)
+    }
+
+    /// Get the results of the applied mutations, along with the keys of the affected entities.
+    pub fn results(&self) -> &[(Key, MutationResult)] {
+        self.written.as_slice()
+    }
+
+    /// Get the failed commits.
+    pub fn failures(&self) -> &[BulkWriteFailure] {
+        self.failures.as_slice()
+    }
+
+    /// Move the failed commits out.
+    pub fn into_failures(self) -> Vec<BulkWriteFailure> {
+        self.failures
+    }
+}
+
+/// Represents a failed commit within a bulk write.
+#[derive(Debug)]
+pub struct BulkWriteFailure {
+    pub(crate) mutations: Vec<Mutation>,
+    pub(crate) error: Error,
+}
+
+impl BulkWriteFailure {
+    /// Get the mutations that have not been applied.
+    pub fn mutations(&self) -> &[Mutation] {
+        self.mutations.as_slice()
+    }
+
+    /// Move the mutations that have not been applied out, to retry them for instance.
+    pub fn into_mutations(self) -> Vec<Mutation> {
+        self.mutations
+    }
+
+    /// Get the error that made the commit fail.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Move the error that made the commit fail out.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+/// Decode a mutation encoded by `convert_mutation`.
+fn restore_mutation(mutation: api::Mutation) -> Mutation {
+    use api::mutation::{ConflictDetectionStrategy, Operation};
+    //? Mutations are encoded by `convert_mutation`, which always sets their operation and keys.
+    let restored = match mutation.operation.unwrap() {
+        Operation::Insert(entity) => Mutation::Insert(Entity::from(entity)),
+        Operation::Update(entity) => Mutation::Update(Entity::from(entity)),
+        Operation::Upsert(entity) => Mutation::Upsert(Entity::from(entity)),
+        Operation::Delete(key) => Mutation::Delete(Key::from(key)),
+    };
+    match mutation.conflict_detection_strategy {
+        Some(ConflictDetectionStrategy::BaseVersion(base_version)) => Mutation::Conditional {
+            mutation: Box::new(restored),
+            base_version,
+        },
+        None => restored,
+    }
+}
+
+/// Get the key targeted by an encoded mutation.
+fn mutation_key(mutation: &api::Mutation) -> Key {
+    use api::mutation::Operation;
+    let key = match mutation.operation.as_ref() {
+        Some(Operation::Insert(entity))
+        | Some(Operation::Update(entity))
+        | Some(Operation::Upsert(entity)) => entity.key.clone(),
+        Some(Operation::Delete(key)) => Some(key.clone()),
+        None => None,
+    };
+    //? Mutations are encoded by `convert_mutation`, which always sets their keys.
+    Key::from(key.unwrap())
+}
@@ -1,19 +1,28 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::env;
 use std::fs::File;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::SubsecRound;
+use futures::future;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use tokio::sync::Mutex;
 use tonic::transport::{Certificate, Channel, ClientTlsConfig};
-use tonic::{IntoRequest, Request};
+use tonic::{Code, IntoRequest, Request};
 
 use crate::authorize::{ApplicationCredentials, TokenManager, TLS_CERTS};
 use crate::datastore::api;
 use crate::datastore::api::datastore_client::DatastoreClient;
 use crate::datastore::api::value::ValueType;
+use crate::datastore::options::ReadConsistency;
 use crate::datastore::{
-    Entity, Error, Filter, FromValue, IntoEntity, Key, KeyID, Order, Query, Value,
+    Aggregation, AggregationQuery, AggregationResult, BulkWriter, Cursor, Entity, Error, Filter,
+    FromEntity, GqlBinding, GqlQuery, IntoEntity, Key, KeyID, Model, Mutation, MutationResult,
+    Order, PropertyMetadata, Query, QueryBatch, ReadOptions, Repository, Transaction, Value,
 };
 
 /// The Datastore client, tied to a specific project.
@@ -21,32 +30,50 @@ use crate::datastore::{
 pub struct Client {
     pub(crate) project_name: String,
     pub(crate) service: DatastoreClient<Channel>,
-    pub(crate) token_manager: Arc<Mutex<TokenManager>>,
+    pub(crate) token_manager: Option<Arc<Mutex<TokenManager>>>,
 }
 
 impl Client {
     pub(crate) const DOMAIN_NAME: &'static str = "datastore.googleapis.com";
+    pub(crate) const EMULATOR_HOST_VAR: &'static str = "DATASTORE_EMULATOR_HOST";
     pub(crate) const ENDPOINT: &'static str = "https://datastore.googleapis.com";
     pub(crate) const SCOPES: [&'static str; 2] = [
         "https://www.googleapis.com/auth/cloud-platform",
         "https://www.googleapis.com/auth/datastore",
     ];
+    pub(crate) const TRANSACTION_ATTEMPTS: u32 = 3;
+    pub(crate) const KEY_PROPERTY: &'static str = "__key__";
+    pub(crate) const LOOKUP_KEYS_LIMIT: usize = 1000;
+    pub(crate) const LOOKUP_CONCURRENCY: usize = 8;
+    pub(crate) const COUNT_ALIAS: &'static str = "count";
+    pub(crate) const NAMESPACE_KIND: &'static str = "__namespace__";
+    pub(crate) const KIND_KIND: &'static str = "__kind__";
+    pub(crate) const PROPERTY_KIND: &'static str = "__property__";
 
     pub(crate) async fn construct_request<T: IntoRequest<T>>(
         &mut self,
         request: T,
     ) -> Result<Request<T>, Error> {
         let mut request = request.into_request();
-        let token = self.token_manager.lock().await.token().await?;
-        let metadata = request.metadata_mut();
-        metadata.insert("authorization", token.parse().unwrap());
+        if let Some(token_manager) = self.token_manager.as_ref() {
+            let token = token_manager.lock().await.token().await?;
+            let metadata = request.metadata_mut();
+            metadata.insert("authorization", token.parse().unwrap());
+        }
         Ok(request)
     }
 
     /// Creates a new client for the specified project.
     ///
     /// Credentials are looked up in the `GOOGLE_APPLICATION_CREDENTIALS` environment variable.
+    /// If the `DATASTORE_EMULATOR_HOST` environment variable is set, the client connects
+    /// to the emulator it designates instead (see [`Client::from_emulator`]).
     pub async fn new(project_name: impl Into<String>) -> Result<Client, Error> {
+        if let Some(host) = env::var_os(Client::EMULATOR_HOST_VAR).filter(|host| !host.is_empty()) {
+            let host = host.into_string().map_err(env::VarError::NotUnicode)?;
+            return Client::from_emulator(project_name, host).await;
+        }
+
         let path = env::var("GOOGLE_APPLICATION_CREDENTIALS")?;
         let file = File::open(path)?;
         let creds = json::from_reader(file)?;
@@ -71,66 +98,209 @@ impl Client {
         Ok(Client {
             project_name: project_name.into(),
             service: DatastoreClient::new(channel),
-            token_manager: Arc::new(Mutex::new(TokenManager::new(
+            token_manager: Some(Arc::new(Mutex::new(TokenManager::new(
                 creds,
                 Client::SCOPES.as_ref(),
-            ))),
+            )))),
+        })
+    }
+
+    /// Creates a new client for the specified project, connected to a Datastore emulator.
+    ///
+    /// The emulator is reached over a plaintext connection, and no credentials are used.
+    /// The host can be given with or without its scheme (like `localhost:8081`, as set by
+    /// `gcloud beta emulators datastore env-init`).
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error};
+    /// # async fn run() -> Result<(), Error> {
+    /// let client = Client::from_emulator("my-project", "localhost:8081").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn from_emulator(
+        project_name: impl Into<String>,
+        host: impl AsRef<str>,
+    ) -> Result<Client, Error> {
+        let host = host.as_ref();
+        let endpoint = if host.contains("://") {
+            String::from(host)
+        } else {
+            format!("http://{}", host)
+        };
+        let channel = Channel::from_shared(endpoint)?.connect().await?;
+
+        Ok(Client {
+            project_name: project_name.into(),
+            service: DatastoreClient::new(channel),
+            token_manager: None,
         })
     }
 
     /// Gets an entity from a key.
+    ///
+    /// The entity can be retrieved as an [`Entity`], or as any type implementing [`FromEntity`].
     pub async fn get<T, K>(&mut self, key: K) -> Result<Option<T>, Error>
     where
         K: Borrow<Key>,
-        T: FromValue,
+        T: FromEntity,
     {
-        let results = self.get_all(Some(key.borrow())).await?;
-        Ok(results.into_iter().next().map(T::from_value).transpose()?)
+        let results = self.get_all_aligned(Some(key.borrow())).await?;
+        Ok(results.into_iter().next().flatten())
     }
 
     /// Gets multiple entities from multiple keys.
+    ///
+    /// Missing entities are skipped, use [`Client::get_all_aligned`] to keep track of them.
     pub async fn get_all<T, K, I>(&mut self, keys: I) -> Result<Vec<T>, Error>
     where
         I: IntoIterator<Item = K>,
         K: Borrow<Key>,
-        T: FromValue,
+        T: FromEntity,
+    {
+        let results = self.lookup(keys, ReadOptions::default()).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Gets multiple entities from multiple keys, using specific read options.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Entity, Error, Key, ReadOptions};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let keys = vec![Key::new("users").id(1), Key::new("users").id(2)];
+    /// let users: Vec<Entity> = client.get_all_with_options(keys, ReadOptions::eventual()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_all_with_options<T, K, I>(
+        &mut self,
+        keys: I,
+        read_options: ReadOptions,
+    ) -> Result<Vec<T>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        let results = self.lookup(keys, read_options).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Gets multiple entities from multiple keys, returning one result per key in the same order.
+    ///
+    /// Missing entities are returned as `None`.
+    /// Large numbers of keys are split into multiple lookups, running a few at a time.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Entity, Error, Key};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let keys = vec![Key::new("users").id(1), Key::new("users").id(2)];
+    /// let users: Vec<Option<Entity>> = client.get_all_aligned(&keys).await?;
+    /// assert_eq!(users.len(), keys.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_all_aligned<T, K, I>(&mut self, keys: I) -> Result<Vec<Option<T>>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        self.lookup(keys, ReadOptions::default()).await
+    }
+
+    /// Gets multiple entities from multiple keys using specific read options,
+    /// returning one result per key in the same order.
+    pub async fn get_all_aligned_with_options<T, K, I>(
+        &mut self,
+        keys: I,
+        read_options: ReadOptions,
+    ) -> Result<Vec<Option<T>>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        self.lookup(keys, read_options).await
+    }
+
+    pub(crate) async fn lookup<T, K, I>(
+        &mut self,
+        keys: I,
+        read_options: ReadOptions,
+    ) -> Result<Vec<Option<T>>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
     {
         let og_keys: Vec<K> = keys.into_iter().collect();
-        let mut keys: Vec<_> = og_keys
+        let keys: Vec<_> = og_keys
             .iter()
             .map(|key| convert_key(self.project_name.as_str(), key.borrow()))
             .collect();
+        let read_options = convert_read_options(read_options);
+
+        let lookups = keys.chunks(Client::LOOKUP_KEYS_LIMIT).map(|keys| {
+            let mut client = self.clone();
+            let read_options = read_options.clone();
+            async move { client.lookup_chunk(keys.to_vec(), read_options).await }
+        });
+        let mut lookups = stream::iter(lookups).buffered(Client::LOOKUP_CONCURRENCY);
+        let mut found = HashMap::new();
+        while let Some(entities) = lookups.try_next().await? {
+            found.extend(entities);
+        }
+
+        //? The same key may be requested more than once, so only the last occurrence takes the entity.
+        let mut occurrences: HashMap<&Key, usize> = HashMap::new();
+        for key in og_keys.iter() {
+            *occurrences.entry(key.borrow()).or_default() += 1;
+        }
+        let values = og_keys
+            .iter()
+            .map(|key| {
+                let key = key.borrow();
+                let remaining = occurrences.get_mut(key).unwrap();
+                *remaining -= 1;
+                let entity = if *remaining == 0 {
+                    found.remove(key)
+                } else {
+                    found.get(key).cloned()
+                };
+                entity.map(FromEntity::from_entity).transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(values)
+    }
+
+    async fn lookup_chunk(
+        &mut self,
+        mut keys: Vec<api::Key>,
+        read_options: api::ReadOptions,
+    ) -> Result<HashMap<Key, Entity>, Error> {
         let mut found = HashMap::new();
 
         while !keys.is_empty() {
             let request = api::LookupRequest {
                 keys,
                 project_id: self.project_name.clone(),
-                read_options: None,
+                read_options: Some(read_options.clone()),
             };
             let request = self.construct_request(request).await?;
             let response = self.service.lookup(request).await?;
             let response = response.into_inner();
 
-            found.extend(
-                response
-                    .found
-                    .into_iter()
-                    .map(|val| val.entity.unwrap())
-                    .map(Entity::from)
-                    .map(|entity| (entity.key, entity.properties)),
-            );
-            // let missing = response.missing;
+            for result in response.found {
+                let entity = Entity::try_from(result)
+                    .map_err(|_| malformed_response("missing entity in lookup result"))?;
+                found.insert(entity.key.clone(), entity);
+            }
             keys = response.deferred;
         }
 
-        let values: Vec<T> = og_keys
-            .into_iter()
-            .flat_map(|key| found.remove(key.borrow()))
-            .map(FromValue::from_value)
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(values)
+        Ok(found)
     }
 
     /// Inserts a new entity and returns its key.
@@ -154,31 +324,11 @@ impl Client {
             .collect::<Result<_, _>>()?;
         let mutations = entities
             .into_iter()
-            .map(|entity| {
-                let is_incomplete = entity.key.is_incomplete();
-                let entity = convert_entity(self.project_name.as_str(), entity);
-                api::Mutation {
-                    operation: if is_incomplete {
-                        Some(api::mutation::Operation::Insert(entity))
-                    } else {
-                        Some(api::mutation::Operation::Upsert(entity))
-                    },
-                    conflict_detection_strategy: None,
-                }
-            })
+            .map(|entity| convert_put(self.project_name.as_str(), entity))
             .collect();
 
-        let request = api::CommitRequest {
-            mutations,
-            mode: api::commit_request::Mode::NonTransactional as i32,
-            transaction_selector: None,
-            project_id: self.project_name.clone(),
-        };
-        let request = self.construct_request(request).await?;
-        let response = self.service.commit(request).await?;
-        let response = response.into_inner();
-        let keys = response
-            .mutation_results
+        let results = self.commit_mutations(mutations, None).await?;
+        let keys = results
             .into_iter()
             .map(|result| result.key.map(Key::from))
             .collect();
@@ -199,116 +349,610 @@ impl Client {
     {
         let mutations = keys
             .into_iter()
-            .map(|key| convert_key(self.project_name.as_str(), key.borrow()))
-            .map(|key| api::Mutation {
-                operation: Some(api::mutation::Operation::Delete(key)),
-                conflict_detection_strategy: None,
-            })
+            .map(|key| Mutation::Delete(key.borrow().clone()))
+            .map(|mutation| convert_mutation(self.project_name.as_str(), mutation))
             .collect();
 
+        self.commit_mutations(mutations, None).await?;
+
+        Ok(())
+    }
+
+    /// Allocates IDs for multiple incomplete keys, and returns the completed keys in the same order.
+    ///
+    /// The allocated IDs are guaranteed to never be assigned by Datastore to other entities,
+    /// which allows building keys referencing each other before writing anything.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error, Key};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let keys = vec![Key::new("users").namespace("dev"), Key::new("users").namespace("dev")];
+    /// let keys = client.allocate_ids(keys).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn allocate_ids<T, I>(&mut self, keys: I) -> Result<Vec<Key>, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Borrow<Key>,
+    {
+        let request = api::AllocateIdsRequest {
+            project_id: self.project_name.clone(),
+            keys: keys
+                .into_iter()
+                .map(|key| convert_key(self.project_name.as_str(), key.borrow()))
+                .collect(),
+        };
+        let request = self.construct_request(request).await?;
+        let response = self.service.allocate_ids(request).await?;
+        let response = response.into_inner();
+
+        Ok(response.keys.into_iter().map(Key::from).collect())
+    }
+
+    /// Reserves the IDs of multiple complete keys, preventing Datastore from assigning them to other entities.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error, Key};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let keys = vec![Key::new("users").id(1), Key::new("users").id(2)];
+    /// client.reserve_ids(keys).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn reserve_ids<T, I>(&mut self, keys: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Borrow<Key>,
+    {
+        let request = api::ReserveIdsRequest {
+            project_id: self.project_name.clone(),
+            keys: keys
+                .into_iter()
+                .map(|key| convert_key(self.project_name.as_str(), key.borrow()))
+                .collect(),
+            ..Default::default()
+        };
+        let request = self.construct_request(request).await?;
+        self.service.reserve_ids(request).await?;
+
+        Ok(())
+    }
+
+    /// Inserts or updates an entity, only if its current version matches the base version,
+    /// and returns its new version.
+    ///
+    /// If the entity has been modified since, this fails with `Error::Conflict`.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Entity, Error, Key, Value};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let key = Key::new("documents").id("readme");
+    /// let mut document: Entity = client.get(&key).await?.expect("missing document");
+    /// let version = document.version().unwrap_or_default();
+    /// if let Value::EntityValue(properties) = document.properties_mut() {
+    ///     properties.insert(String::from("title"), Value::StringValue(String::from("README")));
+    /// }
+    /// client.put_if_version(document, version).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn put_if_version(
+        &mut self,
+        entity: impl IntoEntity,
+        base_version: i64,
+    ) -> Result<i64, Error> {
+        let entity = entity.into_entity()?;
+        let mutation = if entity.key.is_incomplete() {
+            Mutation::Insert(entity)
+        } else {
+            Mutation::Upsert(entity)
+        };
+        self.commit_if_version(mutation.base_version(base_version))
+            .await
+    }
+
+    /// Deletes an entity identified by a key, only if its current version matches the base version.
+    ///
+    /// If the entity has been modified since, this fails with `Error::Conflict`.
+    pub async fn delete_if_version(
+        &mut self,
+        key: impl Borrow<Key>,
+        base_version: i64,
+    ) -> Result<(), Error> {
+        let mutation = Mutation::Delete(key.borrow().clone());
+        self.commit_if_version(mutation.base_version(base_version))
+            .await?;
+        Ok(())
+    }
+
+    async fn commit_if_version(&mut self, mutation: Mutation) -> Result<i64, Error> {
+        let key = mutation.key().clone();
+        let mutation = convert_mutation(self.project_name.as_str(), mutation);
+        let results = self.commit_mutations(vec![mutation], None).await?;
+        let result = results
+            .into_iter()
+            .next()
+            .map(MutationResult::from)
+            .ok_or_else(|| malformed_response("missing mutation result"))?;
+        if result.conflict_detected() {
+            Err(Error::Conflict(key))
+        } else {
+            Ok(result.version())
+        }
+    }
+
+    /// Creates a bulk writer, to write large amounts of mutations using multiple concurrent commits.
+    pub fn bulk_writer(&self) -> BulkWriter {
+        BulkWriter::new(self.clone())
+    }
+
+    /// Creates a repository, to access the entities of a model in a typed way.
+    pub fn repository<M: Model>(&self) -> Repository<M> {
+        Repository::new(self.clone())
+    }
+
+    /// Applies multiple mutations and returns their results, in the same order.
+    ///
+    /// Each mutation keeps its exact semantics: inserting an existing entity or updating a missing one
+    /// fails the whole request.
+    /// The mutations are not applied atomically (use a [`Transaction`] for this), and no two mutations
+    /// may affect the same entity.
+    pub async fn commit<I>(&mut self, mutations: I) -> Result<Vec<MutationResult>, Error>
+    where
+        I: IntoIterator<Item = Mutation>,
+    {
+        let mutations = mutations
+            .into_iter()
+            .map(|mutation| convert_mutation(self.project_name.as_str(), mutation))
+            .collect();
+        let results = self.commit_mutations(mutations, None).await?;
+
+        Ok(results.into_iter().map(MutationResult::from).collect())
+    }
+
+    /// Begins a new read-write transaction.
+    ///
+    /// Mutations made through the returned handle are buffered and only applied, atomically,
+    /// once [`Transaction::commit`] is called.
+    pub async fn begin_transaction(&mut self) -> Result<Transaction, Error> {
+        self.begin_transaction_with(None).await
+    }
+
+    pub(crate) async fn begin_transaction_with(
+        &mut self,
+        previous_transaction: Option<Vec<u8>>,
+    ) -> Result<Transaction, Error> {
+        use api::transaction_options::{Mode, ReadWrite};
+        let request = api::BeginTransactionRequest {
+            project_id: self.project_name.clone(),
+            transaction_options: Some(api::TransactionOptions {
+                mode: Some(Mode::ReadWrite(ReadWrite {
+                    previous_transaction: previous_transaction.unwrap_or_default(),
+                })),
+            }),
+        };
+        let request = self.construct_request(request).await?;
+        let response = self.service.begin_transaction(request).await?;
+        let response = response.into_inner();
+
+        Ok(Transaction::new(self.clone(), response.transaction))
+    }
+
+    /// Runs a closure within a read-write transaction and commits the mutations it made.
+    ///
+    /// If the transaction fails to commit because of contention (`ABORTED` status),
+    /// the closure is called again with a fresh transaction, up to a fixed number of attempts.
+    /// The transaction is rolled back if the closure returns an error.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error, Key};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let key = Key::new("counters").id("visits");
+    /// client
+    ///     .run_in_transaction(|mut tx| {
+    ///         let key = key.clone();
+    ///         async move {
+    ///             let count: Option<i64> = tx.get(&key).await?;
+    ///             tx.put((key, count.unwrap_or(0) + 1))?;
+    ///             Ok(())
+    ///         }
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_in_transaction<F, Fut, T>(&mut self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut(Transaction) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut previous_transaction = None;
+        let mut attempt = 1;
+        loop {
+            let tx = self
+                .begin_transaction_with(previous_transaction.take())
+                .await?;
+            let id = tx.id.clone();
+            let outcome = match f(tx.clone()).await {
+                Ok(value) => tx.commit().await.map(|_| value),
+                Err(err) => {
+                    //? The original error matters more than a failed rollback.
+                    let _ = tx.rollback().await;
+                    Err(err)
+                }
+            };
+            match outcome {
+                Err(Error::Status(ref status))
+                    if status.code() == Code::Aborted && attempt < Client::TRANSACTION_ATTEMPTS =>
+                {
+                    let backoff = Duration::from_millis(100 * 2u64.pow(attempt - 1));
+                    tokio::time::sleep(backoff).await;
+                    previous_transaction = Some(id);
+                    attempt += 1;
+                }
+                outcome => break outcome,
+            }
+        }
+    }
+
+    pub(crate) async fn commit_mutations(
+        &mut self,
+        mutations: Vec<api::Mutation>,
+        transaction: Option<Vec<u8>>,
+    ) -> Result<Vec<api::MutationResult>, Error> {
+        let project_name = self.project_name.clone();
+        self.commit_mutations_to(project_name, mutations, transaction)
+            .await
+    }
+
+    pub(crate) async fn commit_mutations_to(
+        &mut self,
+        project_name: String,
+        mutations: Vec<api::Mutation>,
+        transaction: Option<Vec<u8>>,
+    ) -> Result<Vec<api::MutationResult>, Error> {
+        use api::commit_request::{Mode, TransactionSelector};
+        let mode = if transaction.is_some() {
+            Mode::Transactional
+        } else {
+            Mode::NonTransactional
+        };
         let request = api::CommitRequest {
             mutations,
-            mode: api::commit_request::Mode::NonTransactional as i32,
-            transaction_selector: None,
+            mode: mode as i32,
+            transaction_selector: transaction.map(TransactionSelector::Transaction),
+            project_id: project_name,
+        };
+        let request = self.construct_request(request).await?;
+        let response = self.service.commit(request).await?;
+        let response = response.into_inner();
+
+        Ok(response.mutation_results)
+    }
+
+    pub(crate) async fn rollback(&mut self, transaction: Vec<u8>) -> Result<(), Error> {
+        let request = api::RollbackRequest {
             project_id: self.project_name.clone(),
+            transaction,
         };
         let request = self.construct_request(request).await?;
-        self.service.commit(request).await?;
+        self.service.rollback(request).await?;
 
         Ok(())
     }
 
     /// Runs a (potentially) complex query againt Datastore and returns the results.
+    ///
+    /// All the results are collected in memory, use [`Client::query_stream`] to process them lazily.
     pub async fn query(&mut self, query: Query) -> Result<Vec<Entity>, Error> {
-        let mut output = Vec::new();
+        self.query_stream(query).try_collect().await
+    }
 
-        let mut cur_query = query.clone();
-        let mut cursor = Vec::new();
-        loop {
-            let projection = cur_query
-                .projections
-                .into_iter()
-                .map(|name| api::Projection {
-                    property: Some(api::PropertyReference { name }),
-                })
-                .collect();
-            let filter = convert_filter(self.project_name.as_str(), cur_query.filters);
-            let order = cur_query
-                .ordering
-                .into_iter()
-                .map(|order| {
-                    use api::property_order::Direction;
-                    let (name, direction) = match order {
-                        Order::Asc(name) => (name, Direction::Ascending),
-                        Order::Desc(name) => (name, Direction::Descending),
-                    };
-                    api::PropertyOrder {
-                        property: Some(api::PropertyReference { name }),
-                        direction: direction as i32,
-                    }
-                })
-                .collect();
-            let api_query = api::Query {
-                kind: vec![api::KindExpression {
-                    name: cur_query.kind,
-                }],
-                projection,
-                filter,
-                order,
-                offset: cur_query.offset,
-                limit: cur_query.limit,
-                start_cursor: cursor,
-                end_cursor: Vec::new(),
-                distinct_on: cur_query
-                    .distinct_on
-                    .into_iter()
-                    .map(|name| api::PropertyReference { name })
-                    .collect(),
-            };
-            let request = api::RunQueryRequest {
-                partition_id: Some(api::PartitionId {
-                    project_id: self.project_name.clone(),
-                    namespace_id: cur_query.namespace.unwrap_or_else(String::new),
-                }),
-                query_type: Some(api::run_query_request::QueryType::Query(api_query)),
-                read_options: Some({
-                    use api::read_options::{ConsistencyType, ReadConsistency};
-                    api::ReadOptions {
-                        consistency_type: Some(ConsistencyType::ReadConsistency(
-                            if cur_query.eventual {
-                                ReadConsistency::Eventual as i32
-                            } else {
-                                ReadConsistency::Strong as i32
-                            },
-                        )),
-                    }
-                }),
+    /// Runs a query againt Datastore and only returns the keys of the matching entities.
+    ///
+    /// The query is turned into a keys-only query, which avoids transferring the entities' properties.
+    pub async fn query_keys(&mut self, query: Query) -> Result<Vec<Key>, Error> {
+        let entities = self.query(query.keys_only()).await?;
+        Ok(entities.into_iter().map(Entity::into_key).collect())
+    }
+
+    /// Runs a query againt Datastore and lazily streams the resulting entities.
+    ///
+    /// Result batches are only fetched from Datastore as the stream gets consumed.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error, Query};
+    /// use futures::TryStreamExt;
+    ///
+    /// # async fn run(client: Client) -> Result<(), Error> {
+    /// let mut entities = client.query_stream(Query::new("users"));
+    /// while let Some(entity) = entities.try_next().await? {
+    ///     println!("{:?}", entity.key());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_stream(
+        &self,
+        query: Query,
+    ) -> impl Stream<Item = Result<Entity, Error>> + Send + Unpin {
+        self.query_batches(query)
+            .map_ok(|batch| stream::iter(batch.into_entities().into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Runs a query againt Datastore and lazily streams the batches of results, as returned by Datastore.
+    ///
+    /// Each batch comes with the cursors needed to resume the query at any of its results,
+    /// which makes it suitable for paginating through results.
+    pub fn query_batches(
+        &self,
+        query: Query,
+    ) -> impl Stream<Item = Result<QueryBatch, Error>> + Send + Unpin {
+        let read_options = query.read_options.clone();
+        self.run_query(query, read_options)
+    }
+
+    /// Runs a GQL query against Datastore and returns the results.
+    ///
+    /// The results can be collected as entities, or as any type implementing [`FromEntity`].
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Entity, Error, GqlQuery};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let query = GqlQuery::new("SELECT * FROM users WHERE age > @age").bind("age", 18);
+    /// let users: Vec<Entity> = client.gql(query).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn gql<T>(&mut self, query: impl Into<GqlQuery>) -> Result<Vec<T>, Error>
+    where
+        T: FromEntity,
+    {
+        self.gql_stream(query)
+            .and_then(|entity| future::ready(T::from_entity(entity).map_err(Error::from)))
+            .try_collect()
+            .await
+    }
+
+    /// Runs a GQL query against Datastore and lazily streams the resulting entities.
+    pub fn gql_stream(
+        &self,
+        query: impl Into<GqlQuery>,
+    ) -> impl Stream<Item = Result<Entity, Error>> + Send + Unpin {
+        let query = query.into();
+        let namespace = query.namespace.clone();
+        let read_options = convert_read_options(query.read_options.clone());
+        let query_type = api::run_query_request::QueryType::GqlQuery(convert_gql_query(
+            self.project_name.as_str(),
+            query,
+        ));
+        self.run_query_type(namespace, query_type, read_options)
+            .map_ok(|batch| stream::iter(batch.into_entities().into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Runs an aggregation query against Datastore and returns the aggregated results.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{AggregationQuery, Client, Error, Query};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let query = AggregationQuery::new(Query::new("users"))
+    ///     .count("total")
+    ///     .avg("average_age", "age");
+    /// let result = client.aggregate(query).await?;
+    /// let total = result.count("total");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn aggregate(
+        &mut self,
+        query: impl Into<AggregationQuery>,
+    ) -> Result<AggregationResult, Error> {
+        let query = query.into();
+        let request = api::RunAggregationQueryRequest {
+            partition_id: Some(api::PartitionId {
                 project_id: self.project_name.clone(),
-            };
-            let request = self.construct_request(request).await?;
-            let results = self.service.run_query(request).await?;
-            let results = results.into_inner().batch.unwrap();
+                namespace_id: query.query.namespace.clone().unwrap_or_default(),
+            }),
+            read_options: Some(convert_read_options(query.query.read_options.clone())),
+            query_type: Some(
+                api::run_aggregation_query_request::QueryType::AggregationQuery(
+                    convert_aggregation_query(self.project_name.as_str(), query),
+                ),
+            ),
+            project_id: self.project_name.clone(),
+        };
+        let request = self.construct_request(request).await?;
+        let response = self.service.run_aggregation_query(request).await?;
+        let response = response.into_inner();
 
-            output.extend(
-                results
-                    .entity_results
-                    .into_iter()
-                    .map(|el| Entity::from(el.entity.unwrap())),
-            );
+        let result = response
+            .batch
+            .and_then(|batch| batch.aggregation_results.into_iter().next())
+            .map(AggregationResult::try_from)
+            .transpose()
+            .map_err(|_| malformed_response("missing value in aggregation result"))?
+            .unwrap_or_else(|| AggregationResult {
+                properties: HashMap::new(),
+            });
+
+        Ok(result)
+    }
+
+    /// Counts the entities matching a query, without fetching them.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error, Query};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// let count = client.count(Query::new("users")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn count(&mut self, query: Query) -> Result<i64, Error> {
+        let query = AggregationQuery::new(query).count(Client::COUNT_ALIAS);
+        let result = self.aggregate(query).await?;
+        Ok(result.count(Client::COUNT_ALIAS).unwrap_or_default())
+    }
+
+    /// Lists the namespaces of the database.
+    ///
+    /// The default namespace is listed as an empty string.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// for namespace in client.namespaces().await? {
+    ///     println!("{}: {:?}", namespace, client.kinds(Some(namespace.as_str())).await?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn namespaces(&mut self) -> Result<Vec<String>, Error> {
+        let keys = self.query_keys(Query::new(Client::NAMESPACE_KIND)).await?;
+        let namespaces = keys.into_iter().map(|key| match key.id {
+            KeyID::StringID(name) => name,
+            _ => String::new(),
+        });
+        Ok(namespaces.collect())
+    }
+
+    /// Lists the kinds of a namespace (or of the default namespace, if `None`).
+    pub async fn kinds(&mut self, namespace: Option<&str>) -> Result<Vec<String>, Error> {
+        let query = Query::new(Client::KIND_KIND);
+        let query = match namespace {
+            Some(namespace) => query.namespace(namespace),
+            None => query,
+        };
+        let keys = self.query_keys(query).await?;
+        let kinds = keys.into_iter().filter_map(|key| match key.id {
+            KeyID::StringID(name) => Some(name),
+            _ => None,
+        });
+        Ok(kinds.collect())
+    }
+
+    /// Lists the indexed properties of a kind of the default namespace, along with their representations.
+    ///
+    /// Properties excluded from indexes are not listed.
+    ///
+    /// ```no_run
+    /// # use google_cloud::datastore::{Client, Error};
+    /// # async fn run(mut client: Client) -> Result<(), Error> {
+    /// for property in client.properties("users").await? {
+    ///     println!("{}: {:?}", property.name(), property.representations());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn properties(&mut self, kind: &str) -> Result<Vec<PropertyMetadata>, Error> {
+        self.properties_in(None, kind).await
+    }
+
+    /// Lists the indexed properties of a kind of a namespace (or of the default namespace, if `None`).
+    pub async fn properties_in(
+        &mut self,
+        namespace: Option<&str>,
+        kind: &str,
+    ) -> Result<Vec<PropertyMetadata>, Error> {
+        let ancestor = Key::new(Client::KIND_KIND).id(kind);
+        let query = Query::new(Client::PROPERTY_KIND);
+        let (ancestor, query) = match namespace {
+            Some(namespace) => (ancestor.namespace(namespace), query.namespace(namespace)),
+            None => (ancestor, query),
+        };
+        let entities = self.query(query.ancestor(ancestor)).await?;
+        let properties = entities.into_iter().map(PropertyMetadata::from_entity);
+        Ok(properties.collect::<Result<_, _>>()?)
+    }
 
-            if results.more_results
-                != (api::query_result_batch::MoreResultsType::NotFinished as i32)
-            {
-                break Ok(output);
+    pub(crate) fn run_query(
+        &self,
+        query: Query,
+        read_options: ReadOptions,
+    ) -> impl Stream<Item = Result<QueryBatch, Error>> + Send + Unpin {
+        let namespace = query.namespace.clone();
+        let read_options = convert_read_options(read_options);
+        let query = convert_query(self.project_name.as_str(), query);
+        let query_type = api::run_query_request::QueryType::Query(query);
+        self.run_query_type(namespace, query_type, read_options)
+    }
+
+    pub(crate) fn run_query_type(
+        &self,
+        namespace: Option<String>,
+        query_type: api::run_query_request::QueryType,
+        read_options: api::ReadOptions,
+    ) -> impl Stream<Item = Result<QueryBatch, Error>> + Send + Unpin {
+        let state = (self.clone(), query_type);
+        let batches = stream::try_unfold(Some(state), move |state| {
+            let namespace = namespace.clone();
+            let read_options = read_options.clone();
+            async move {
+                let (mut client, query_type) = match state {
+                    Some(state) => state,
+                    None => return Ok(None),
+                };
+                let request = api::RunQueryRequest {
+                    partition_id: Some(api::PartitionId {
+                        project_id: client.project_name.clone(),
+                        namespace_id: namespace.unwrap_or_default(),
+                    }),
+                    query_type: Some(query_type.clone()),
+                    read_options: Some(read_options),
+                    project_id: client.project_name.clone(),
+                };
+                let request = client.construct_request(request).await?;
+                let response = client.service.run_query(request).await?;
+                let response = response.into_inner();
+                let batch = response
+                    .batch
+                    .ok_or_else(|| malformed_response("missing query results"))?;
+
+                let next = if batch.more_results
+                    == (api::query_result_batch::MoreResultsType::NotFinished as i32)
+                {
+                    //? GQL queries are continued using the parsed query sent back by Datastore.
+                    let query = match (response.query, query_type) {
+                        (Some(query), _) => query,
+                        (None, api::run_query_request::QueryType::Query(query)) => query,
+                        (None, api::run_query_request::QueryType::GqlQuery(_)) => {
+                            return Err(malformed_response("missing parsed GQL query"));
+                        }
+                    };
+                    let query = api::Query {
+                        start_cursor: batch.end_cursor.clone(),
+                        offset: (query.offset - batch.skipped_results).max(0),
+                        limit: query
+                            .limit
+                            .map(|limit| limit - batch.entity_results.len() as i32),
+                        ..query
+                    };
+                    Some((client, api::run_query_request::QueryType::Query(query)))
+                } else {
+                    None
+                };
+
+                let batch = QueryBatch::try_from(batch)
+                    .map_err(|_| malformed_response("missing entity in query result"))?;
+                Ok(Some((batch, next)))
             }
+        });
 
-            cur_query = query.clone();
-            cursor = results.end_cursor;
-        }
+        Box::pin(batches)
     }
 }
 
-fn convert_key(project_name: &str, key: &Key) -> api::Key {
+/// Reports a response from Datastore lacking data it should always contain.
+fn malformed_response(message: &str) -> Error {
+    Error::Status(tonic::Status::internal(format!(
+        "malformed response from Datastore: {}",
+        message
+    )))
+}
+
+pub(crate) fn convert_key(project_name: &str, key: &Key) -> api::Key {
     api::Key {
         partition_id: Some(api::PartitionId {
             project_id: String::from(project_name),
@@ -336,15 +980,191 @@ fn convert_key(project_name: &str, key: &Key) -> api::Key {
     }
 }
 
-fn convert_entity(project_name: &str, entity: Entity) -> api::Entity {
+pub(crate) fn convert_put(project_name: &str, entity: Entity) -> api::Mutation {
+    let mutation = if entity.key.is_incomplete() {
+        Mutation::Insert(entity)
+    } else {
+        Mutation::Upsert(entity)
+    };
+    convert_mutation(project_name, mutation)
+}
+
+pub(crate) fn convert_mutation(project_name: &str, mutation: Mutation) -> api::Mutation {
+    use api::mutation::ConflictDetectionStrategy;
+    use api::mutation::Operation;
+    let operation = match mutation {
+        Mutation::Conditional {
+            mutation,
+            base_version,
+        } => {
+            return api::Mutation {
+                conflict_detection_strategy: Some(ConflictDetectionStrategy::BaseVersion(
+                    base_version,
+                )),
+                ..convert_mutation(project_name, *mutation)
+            };
+        }
+        Mutation::Insert(entity) => Operation::Insert(convert_entity(project_name, entity)),
+        Mutation::Update(entity) => Operation::Update(convert_entity(project_name, entity)),
+        Mutation::Upsert(entity) => Operation::Upsert(convert_entity(project_name, entity)),
+        Mutation::Delete(key) => Operation::Delete(convert_key(project_name, &key)),
+    };
+    api::Mutation {
+        operation: Some(operation),
+        conflict_detection_strategy: None,
+    }
+}
+
+pub(crate) fn convert_query(project_name: &str, query: Query) -> api::Query {
+    let projections = if query.keys_only && query.projections.is_empty() {
+        vec![String::from(Client::KEY_PROPERTY)]
+    } else {
+        query.projections
+    };
+    let projection = projections
+        .into_iter()
+        .map(|name| api::Projection {
+            property: Some(api::PropertyReference { name }),
+        })
+        .collect();
+    let filter = convert_filter(project_name, query.filters, query.ancestor);
+    let order = query
+        .ordering
+        .into_iter()
+        .map(|order| {
+            use api::property_order::Direction;
+            let (name, direction) = match order {
+                Order::Asc(name) => (name, Direction::Ascending),
+                Order::Desc(name) => (name, Direction::Descending),
+            };
+            api::PropertyOrder {
+                property: Some(api::PropertyReference { name }),
+                direction: direction as i32,
+            }
+        })
+        .collect();
+    api::Query {
+        kind: vec![api::KindExpression { name: query.kind }],
+        projection,
+        filter,
+        order,
+        offset: query.offset,
+        limit: query.limit,
+        start_cursor: query
+            .start_cursor
+            .map(Cursor::into_bytes)
+            .unwrap_or_default(),
+        end_cursor: query.end_cursor.map(Cursor::into_bytes).unwrap_or_default(),
+        distinct_on: query
+            .distinct_on
+            .into_iter()
+            .map(|name| api::PropertyReference { name })
+            .collect(),
+    }
+}
+
+fn convert_aggregation_query(project_name: &str, query: AggregationQuery) -> api::AggregationQuery {
+    use api::aggregation_query::aggregation::{Avg, Count, Operator, Sum};
+    let aggregations = query
+        .aggregations
+        .into_iter()
+        .map(|aggregation| {
+            let (alias, operator) = match aggregation {
+                Aggregation::Count { alias, up_to } => (alias, Operator::Count(Count { up_to })),
+                Aggregation::Sum { alias, property } => (
+                    alias,
+                    Operator::Sum(Sum {
+                        property: Some(api::PropertyReference { name: property }),
+                    }),
+                ),
+                Aggregation::Avg { alias, property } => (
+                    alias,
+                    Operator::Avg(Avg {
+                        property: Some(api::PropertyReference { name: property }),
+                    }),
+                ),
+            };
+            api::aggregation_query::Aggregation {
+                alias,
+                operator: Some(operator),
+            }
+        })
+        .collect();
+    api::AggregationQuery {
+        query_type: Some(api::aggregation_query::QueryType::NestedQuery(
+            convert_query(project_name, query.query),
+        )),
+        aggregations,
+    }
+}
+
+fn convert_gql_query(project_name: &str, query: GqlQuery) -> api::GqlQuery {
+    let convert_binding = |binding: GqlBinding| {
+        use api::gql_query_parameter::ParameterType;
+        let parameter_type = match binding {
+            GqlBinding::Value(value) => ParameterType::Value(convert_value(project_name, value)),
+            GqlBinding::Cursor(cursor) => ParameterType::Cursor(cursor.into_bytes()),
+        };
+        api::GqlQueryParameter {
+            parameter_type: Some(parameter_type),
+        }
+    };
+    api::GqlQuery {
+        query_string: query.query_string,
+        allow_literals: query.allow_literals,
+        named_bindings: query
+            .named_bindings
+            .into_iter()
+            .map(|(name, binding)| (name, convert_binding(binding)))
+            .collect(),
+        positional_bindings: query
+            .positional_bindings
+            .into_iter()
+            .map(convert_binding)
+            .collect(),
+    }
+}
+
+pub(crate) fn convert_read_options(read_options: ReadOptions) -> api::ReadOptions {
+    use api::read_options::{self, ConsistencyType};
+    let consistency_type = match read_options.consistency {
+        ReadConsistency::Strong => {
+            ConsistencyType::ReadConsistency(read_options::ReadConsistency::Strong as i32)
+        }
+        ReadConsistency::Eventual => {
+            ConsistencyType::ReadConsistency(read_options::ReadConsistency::Eventual as i32)
+        }
+        ReadConsistency::Transaction(id) => ConsistencyType::Transaction(id),
+        ReadConsistency::ReadTime(time) => ConsistencyType::ReadTime(prost_types::Timestamp {
+            seconds: time.timestamp(),
+            nanos: time.timestamp_subsec_nanos() as i32,
+        }),
+    };
+    api::ReadOptions {
+        consistency_type: Some(consistency_type),
+    }
+}
+
+pub(crate) fn convert_entity(project_name: &str, entity: Entity) -> api::Entity {
     let key = convert_key(project_name, &entity.key);
+    let excluded_from_indexes = entity.excluded_from_indexes;
+    let meanings = entity.meanings;
     let properties = match entity.properties {
         Value::EntityValue(properties) => properties,
         _ => panic!("unexpected non-entity datastore value"),
     };
     let properties = properties
         .into_iter()
-        .map(|(k, v)| (k, convert_value(project_name, v)))
+        .map(|(name, value)| {
+            let value = convert_property(
+                project_name,
+                &name,
+                value,
+                &excluded_from_indexes,
+                &meanings,
+            );
+            (name, value)
+        })
         .collect();
     api::Entity {
         key: Some(key),
@@ -352,14 +1172,70 @@ fn convert_entity(project_name: &str, entity: Entity) -> api::Entity {
     }
 }
 
-fn convert_value(project_name: &str, value: Value) -> api::Value {
+fn convert_property(
+    project_name: &str,
+    path: &str,
+    value: Value,
+    excluded_from_indexes: &HashSet<String>,
+    meanings: &HashMap<String, i32>,
+) -> api::Value {
     let value_type = match value {
+        Value::EntityValue(properties) => ValueType::EntityValue(api::Entity {
+            key: None,
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| {
+                    let path = format!("{}.{}", path, name);
+                    let value = convert_property(
+                        project_name,
+                        &path,
+                        value,
+                        excluded_from_indexes,
+                        meanings,
+                    );
+                    (name, value)
+                })
+                .collect(),
+        }),
+        //? Datastore expects index exclusions and meanings on the elements, not on the array itself.
+        Value::ArrayValue(values) => {
+            return api::Value {
+                meaning: 0,
+                exclude_from_indexes: false,
+                value_type: Some(ValueType::ArrayValue(api::ArrayValue {
+                    values: values
+                        .into_iter()
+                        .map(|value| {
+                            convert_property(
+                                project_name,
+                                path,
+                                value,
+                                excluded_from_indexes,
+                                meanings,
+                            )
+                        })
+                        .collect(),
+                })),
+            };
+        }
+        value => convert_value(project_name, value).value_type.unwrap(),
+    };
+    api::Value {
+        meaning: meanings.get(path).copied().unwrap_or_default(),
+        exclude_from_indexes: excluded_from_indexes.contains(path),
+        value_type: Some(value_type),
+    }
+}
+
+pub(crate) fn convert_value(project_name: &str, value: Value) -> api::Value {
+    let value_type = match value {
+        Value::NullValue => ValueType::NullValue(0),
         Value::BooleanValue(val) => ValueType::BooleanValue(val),
         Value::IntegerValue(val) => ValueType::IntegerValue(val),
         Value::DoubleValue(val) => ValueType::DoubleValue(val),
         Value::TimestampValue(val) => ValueType::TimestampValue(prost_types::Timestamp {
             seconds: val.timestamp(),
-            nanos: val.timestamp_subsec_nanos() as i32,
+            nanos: val.trunc_subsecs(6).timestamp_subsec_nanos() as i32,
         }),
         Value::KeyValue(key) => ValueType::KeyValue(convert_key(project_name, &key)),
         Value::StringValue(val) => ValueType::StringValue(val),
@@ -391,43 +1267,78 @@ fn convert_value(project_name: &str, value: Value) -> api::Value {
     }
 }
 
-fn convert_filter(project_name: &str, filters: Vec<Filter>) -> Option<api::Filter> {
-    use api::filter::FilterType;
+fn convert_filter(
+    project_name: &str,
+    mut filters: Vec<Filter>,
+    ancestor: Option<Key>,
+) -> Option<api::Filter> {
+    filters.extend(ancestor.map(Filter::HasAncestor));
 
     if !filters.is_empty() {
-        let filters = filters
-            .into_iter()
-            .map(|filter| {
-                use api::property_filter::Operator;
-                let (name, op, value) = match filter {
-                    Filter::Equal(name, value) => (name, Operator::Equal, value),
-                    Filter::GreaterThan(name, value) => (name, Operator::GreaterThan, value),
-                    Filter::LesserThan(name, value) => (name, Operator::LessThan, value),
-                    Filter::GreaterThanOrEqual(name, value) => {
-                        (name, Operator::GreaterThanOrEqual, value)
-                    }
-                    Filter::LesserThanEqual(name, value) => {
-                        (name, Operator::LessThanOrEqual, value)
-                    }
-                };
+        Some(convert_filter_tree(project_name, Filter::And(filters)))
+    } else {
+        None
+    }
+}
 
-                api::Filter {
-                    filter_type: Some(FilterType::PropertyFilter(api::PropertyFilter {
-                        op: op as i32,
-                        property: Some(api::PropertyReference { name }),
-                        value: Some(convert_value(project_name, value)),
-                    })),
-                }
-            })
-            .collect();
+fn convert_filter_tree(project_name: &str, filter: Filter) -> api::Filter {
+    use api::filter::FilterType;
+    use api::property_filter::Operator;
 
-        Some(api::Filter {
-            filter_type: Some(FilterType::CompositeFilter(api::CompositeFilter {
-                op: api::composite_filter::Operator::And as i32,
+    let (name, op, value) = match filter {
+        Filter::And(filters) => {
+            return convert_composite_filter(
+                project_name,
+                api::composite_filter::Operator::And,
                 filters,
-            })),
-        })
-    } else {
-        None
+            )
+        }
+        Filter::Or(filters) => {
+            return convert_composite_filter(
+                project_name,
+                api::composite_filter::Operator::Or,
+                filters,
+            )
+        }
+        Filter::Equal(name, value) => (name, Operator::Equal, value),
+        Filter::NotEqual(name, value) => (name, Operator::NotEqual, value),
+        Filter::GreaterThan(name, value) => (name, Operator::GreaterThan, value),
+        Filter::LesserThan(name, value) => (name, Operator::LessThan, value),
+        Filter::GreaterThanOrEqual(name, value) => (name, Operator::GreaterThanOrEqual, value),
+        Filter::LesserThanEqual(name, value) => (name, Operator::LessThanOrEqual, value),
+        Filter::In(name, values) => (name, Operator::In, Value::ArrayValue(values)),
+        Filter::NotIn(name, values) => (name, Operator::NotIn, Value::ArrayValue(values)),
+        Filter::HasAncestor(key) => (
+            String::from(Client::KEY_PROPERTY),
+            Operator::HasAncestor,
+            Value::KeyValue(key),
+        ),
+    };
+
+    api::Filter {
+        filter_type: Some(FilterType::PropertyFilter(api::PropertyFilter {
+            op: op as i32,
+            property: Some(api::PropertyReference { name }),
+            value: Some(convert_value(project_name, value)),
+        })),
+    }
+}
+
+fn convert_composite_filter(
+    project_name: &str,
+    op: api::composite_filter::Operator,
+    filters: Vec<Filter>,
+) -> api::Filter {
+    let filters = filters
+        .into_iter()
+        .map(|filter| convert_filter_tree(project_name, filter))
+        .collect();
+    api::Filter {
+        filter_type: Some(api::filter::FilterType::CompositeFilter(
+            api::CompositeFilter {
+                op: op as i32,
+                filters,
+            },
+        )),
     }
 }
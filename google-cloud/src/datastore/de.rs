@@ -0,0 +1,341 @@
+use std::collections::hash_map;
+use std::vec;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use crate::datastore::key::KEY_NEWTYPE_NAME;
+use crate::datastore::Value;
+use crate::error::ConvertError;
+
+/// Converts a Datastore value to any type implementing `serde::Deserialize`.
+///
+/// This is the inverse of [`to_value`](crate::datastore::to_value).
+/// Additionally, timestamps are deserialized as RFC 3339 strings and geographic points as
+/// `(latitude, longitude)` tuples, while key values can only be deserialized as [`Key`]s.
+///
+/// ```
+/// # use google_cloud::datastore::{self, IntoValue, Value};
+/// use std::collections::HashMap;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     #[serde(rename = "fullName")]
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// let mut properties = HashMap::new();
+/// properties.insert(String::from("fullName"), "John Doe".into_value());
+/// let user: User = datastore::from_value(Value::EntityValue(properties)).unwrap();
+/// assert_eq!(user.name, "John Doe");
+/// assert_eq!(user.age, None);
+/// ```
+///
+/// [`Key`]: crate::datastore::Key
+pub fn from_value<T>(value: Value) -> Result<T, ConvertError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ConvertError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::NullValue => visitor.visit_unit(),
+            Value::BooleanValue(value) => visitor.visit_bool(value),
+            Value::IntegerValue(value) => visitor.visit_i64(value),
+            Value::DoubleValue(value) => visitor.visit_f64(value),
+            Value::TimestampValue(value) => visitor.visit_string(value.to_rfc3339()),
+            Value::KeyValue(_) => Err(ConvertError::UnexpectedPropertyType {
+                expected: String::from("deserializable value"),
+                got: String::from(self.type_name()),
+            }),
+            Value::StringValue(value) => visitor.visit_string(value),
+            Value::BlobValue(value) => visitor.visit_byte_buf(value),
+            Value::GeoPointValue(latitude, longitude) => visitor.visit_seq(ArrayAccess {
+                values: vec![Value::DoubleValue(latitude), Value::DoubleValue(longitude)]
+                    .into_iter(),
+            }),
+            Value::EntityValue(properties) => visitor.visit_map(EntityAccess {
+                properties: properties.into_iter(),
+                value: None,
+            }),
+            Value::ArrayValue(values) => visitor.visit_seq(ArrayAccess {
+                values: values.into_iter(),
+            }),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::NullValue => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            //? Allows deserializing blobs into `Vec<u8>`, which serde treats as a sequence.
+            Value::BlobValue(bytes) => visitor.visit_seq(ArrayAccess {
+                values: bytes
+                    .into_iter()
+                    .map(|byte| Value::IntegerValue(i64::from(byte)))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            //? Keys are deserialized from their textual form.
+            Value::KeyValue(key) if name == KEY_NEWTYPE_NAME => {
+                visitor.visit_newtype_struct(Value::StringValue(key.to_string()))
+            }
+            value => visitor.visit_newtype_struct(value),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::StringValue(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::EntityValue(properties) if properties.len() == 1 => {
+                let (variant, value) = properties.into_iter().next().unwrap();
+                visitor.visit_enum(VariantAccess { variant, value })
+            }
+            value => Err(ConvertError::UnexpectedPropertyType {
+                expected: String::from("enum variant"),
+                got: String::from(value.type_name()),
+            }),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ConvertError> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+struct ArrayAccess {
+    values: vec::IntoIter<Value>,
+}
+
+impl<'de> de::SeqAccess<'de> for ArrayAccess {
+    type Error = ConvertError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, ConvertError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(value))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct EntityAccess {
+    properties: hash_map::IntoIter<String, Value>,
+    value: Option<Value>,
+}
+
+impl<'de> de::MapAccess<'de> for EntityAccess {
+    type Error = ConvertError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, ConvertError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.properties.next() {
+            Some((name, value)) => {
+                self.value = Some(value);
+                seed.deserialize(PropertyNameDeserializer(name)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, ConvertError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("`next_value_seed` called before `next_key_seed`");
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.properties.len())
+    }
+}
+
+struct VariantAccess {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess {
+    type Error = ConvertError;
+    type Variant = Value;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Value), ConvertError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = ConvertError;
+
+    fn unit_variant(self) -> Result<(), ConvertError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, ConvertError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserializes property names into map keys, parsing them when integers are expected.
+struct PropertyNameDeserializer(String);
+
+macro_rules! deserialize_parsed_name {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+            where
+                V: Visitor<'de>,
+            {
+                let value = self.0.parse().map_err(|_| ConvertError::UnexpectedPropertyType {
+                    expected: String::from("integer"),
+                    got: String::from("string"),
+                })?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for PropertyNameDeserializer {
+    type Error = ConvertError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    deserialize_parsed_name! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -1,20 +1,65 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::ops::Index;
+
 use crate::datastore::api;
-use crate::datastore::{IntoValue, Key, Value};
+use crate::datastore::{FromValue, IntoValue, Key, Value};
 use crate::error::ConvertError;
 
 /// Represents a Datastore entity.
+///
+/// Alongside its properties, an entity keeps track of which properties are excluded from indexes
+/// and of their meanings.
+/// Properties are identified by their path, with nested properties being separated by dots (like `address.city`).
+/// For arrays, these apply to each of their elements.
+///
+/// Properties can be read, written and removed by their path, and indexing an entity by a path
+/// yields the property's value (or a null value if it is missing).
+///
+/// ```
+/// # use google_cloud::datastore::{Entity, Key};
+/// # use google_cloud::value;
+/// let properties = value! {
+///     "name": "John Doe",
+///     "address": { "city": "Paris" },
+/// };
+/// let mut entity = Entity::new(Key::new("users"), properties).unwrap();
+/// let city: String = entity.get("address.city").unwrap();
+/// assert_eq!(city, "Paris");
+///
+/// entity.set("address.country", "France").unwrap();
+/// assert_eq!(entity["address.country"].as_str(), Some("France"));
+/// assert!(entity.remove("name").is_some());
+/// assert!(entity["name"].is_null());
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct Entity {
     pub(crate) key: Key,
     pub(crate) properties: Value,
+    pub(crate) excluded_from_indexes: HashSet<String>,
+    pub(crate) meanings: HashMap<String, i32>,
+    pub(crate) version: Option<i64>,
 }
 
 impl Entity {
     /// Constructs a new Entity.
-    pub fn new(key: Key, value: impl IntoValue) -> Result<Entity, ConvertError> {
+    ///
+    /// The properties excluded from indexes by the value's type (see [`IntoValue::excluded_from_indexes`])
+    /// are excluded from the entity's indexes.
+    pub fn new<V>(key: Key, value: V) -> Result<Entity, ConvertError>
+    where
+        V: IntoValue,
+    {
+        let excluded_from_indexes = V::excluded_from_indexes().into_iter().collect();
         let properties = value.into_value();
         match properties {
-            Value::EntityValue(_) => Ok(Entity { key, properties }),
+            Value::EntityValue(_) => Ok(Entity {
+                key,
+                properties,
+                excluded_from_indexes,
+                meanings: HashMap::new(),
+                version: None,
+            }),
             _ => Err(ConvertError::UnexpectedPropertyType {
                 expected: String::from("entity"),
                 got: String::from(properties.type_name()),
@@ -46,6 +91,195 @@ impl Entity {
     pub fn properties_mut(&mut self) -> &mut Value {
         &mut self.properties
     }
+
+    /// Get the value of a property, by its path.
+    ///
+    /// Missing properties (or properties whose parent entity is missing or null) are handled by
+    /// [`FromValue::from_missing`], so they can be read as an `Option`.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Entity, Key};
+    /// # use google_cloud::value;
+    /// let entity = Entity::new(Key::new("users"), value! { "address": { "city": "Paris" } }).unwrap();
+    /// let city: String = entity.get("address.city").unwrap();
+    /// let zip: Option<String> = entity.get("address.zip").unwrap();
+    /// assert_eq!(city, "Paris");
+    /// assert_eq!(zip, None);
+    /// assert!(entity.get::<i64>("address.city").is_err());
+    /// ```
+    pub fn get<T>(&self, path: &str) -> Result<T, ConvertError>
+    where
+        T: FromValue,
+    {
+        let mut current = &self.properties;
+        for name in path.split('.') {
+            current = match current {
+                Value::EntityValue(properties) => match properties.get(name) {
+                    Some(value) => value,
+                    None => return T::from_missing(path),
+                },
+                Value::NullValue => return T::from_missing(path),
+                value => return Err(not_an_entity(value)),
+            };
+        }
+        T::from_value(current.clone())
+    }
+
+    /// Set the value of a property, by its path.
+    ///
+    /// The missing (or null) parent entities of the property are created along the way, but
+    /// this fails if one of them is another type of value.
+    /// The index exclusions and meanings recorded for the previous value (and its nested
+    /// properties) are cleared.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Entity, Key};
+    /// # use google_cloud::value;
+    /// let mut entity = Entity::new(Key::new("users"), value! { "name": "John Doe" }).unwrap()
+    ///     .exclude_from_indexes("name");
+    /// entity.set("address.city", "Paris").unwrap();
+    /// assert_eq!(entity["address"]["city"].as_str(), Some("Paris"));
+    /// assert!(entity.set("name.first", "John").is_err());
+    /// entity.set("name", value! { "first": "John" }).unwrap();
+    /// assert!(!entity.is_excluded_from_indexes("name"));
+    /// ```
+    pub fn set(&mut self, path: &str, value: impl IntoValue) -> Result<(), ConvertError> {
+        let (parents, name) = match path.rfind('.') {
+            Some(index) => (Some(&path[..index]), &path[index + 1..]),
+            None => (None, path),
+        };
+        let mut current = &mut self.properties;
+        for parent in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            current = match current {
+                Value::EntityValue(properties) => properties
+                    .entry(String::from(parent))
+                    .or_insert_with(|| Value::EntityValue(HashMap::new())),
+                value => return Err(not_an_entity(value)),
+            };
+            if let Value::NullValue = current {
+                *current = Value::EntityValue(HashMap::new());
+            }
+        }
+        match current {
+            Value::EntityValue(properties) => {
+                properties.insert(String::from(name), value.into_value());
+            }
+            value => return Err(not_an_entity(value)),
+        }
+        self.forget_property(path);
+        Ok(())
+    }
+
+    /// Remove a property, by its path, and return its value if it was present.
+    ///
+    /// The index exclusions and meanings recorded for the property (and its nested properties)
+    /// are cleared along with it.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Entity, Key, Value};
+    /// # use google_cloud::value;
+    /// let mut entity = Entity::new(Key::new("users"), value! { "address": { "city": "Paris" } }).unwrap()
+    ///     .exclude_from_indexes("address.city");
+    /// assert_eq!(entity.remove("address"), Some(value! { "city": "Paris" }));
+    /// assert_eq!(entity.remove("address.city"), None);
+    /// assert!(!entity.is_excluded_from_indexes("address.city"));
+    /// ```
+    pub fn remove(&mut self, path: &str) -> Option<Value> {
+        let (parents, name) = match path.rfind('.') {
+            Some(index) => (Some(&path[..index]), &path[index + 1..]),
+            None => (None, path),
+        };
+        let mut current = &mut self.properties;
+        for parent in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            current = match current {
+                Value::EntityValue(properties) => properties.get_mut(parent)?,
+                _ => return None,
+            };
+        }
+        let removed = match current {
+            Value::EntityValue(properties) => properties.remove(name),
+            _ => None,
+        };
+        if removed.is_some() {
+            self.forget_property(path);
+        }
+        removed
+    }
+
+    /// Clears the index exclusions and meanings recorded for a property and its nested properties.
+    fn forget_property(&mut self, path: &str) {
+        let recorded = |property: &str| {
+            property == path
+                || (property.starts_with(path) && property[path.len()..].starts_with('.'))
+        };
+        self.excluded_from_indexes
+            .retain(|property| !recorded(property));
+        self.meanings.retain(|property, _| !recorded(property));
+    }
+
+    /// Get the version of the entity, if it has been read from Datastore.
+    ///
+    /// The version changes every time the entity is written, which allows to detect concurrent
+    /// writes by using it as the base version of a [`Mutation`](crate::datastore::Mutation).
+    pub fn version(&self) -> Option<i64> {
+        self.version
+    }
+
+    /// Exclude a property from the indexes, allowing to store large strings and blobs.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Entity, Key};
+    /// # use std::collections::HashMap;
+    /// let mut properties = HashMap::new();
+    /// properties.insert(String::from("biography"), "...");
+    /// let entity = Entity::new(Key::new("users"), properties).unwrap()
+    ///     .exclude_from_indexes("biography");
+    /// assert!(entity.is_excluded_from_indexes("biography"));
+    /// ```
+    pub fn exclude_from_indexes(mut self, property: impl Into<String>) -> Entity {
+        self.excluded_from_indexes.insert(property.into());
+        self
+    }
+
+    /// Set whether a property is excluded from the indexes.
+    pub fn set_excluded_from_indexes(&mut self, property: impl Into<String>, excluded: bool) {
+        let property = property.into();
+        if excluded {
+            self.excluded_from_indexes.insert(property);
+        } else {
+            self.excluded_from_indexes.remove(&property);
+        }
+    }
+
+    /// Check whether a property is excluded from the indexes.
+    pub fn is_excluded_from_indexes(&self, property: &str) -> bool {
+        self.excluded_from_indexes.contains(property)
+    }
+
+    /// Get the paths of all the properties excluded from the indexes.
+    pub fn excluded_from_indexes(&self) -> impl Iterator<Item = &str> {
+        self.excluded_from_indexes.iter().map(String::as_str)
+    }
+
+    /// Get the meaning of a property, if it has any.
+    pub fn meaning(&self, property: &str) -> Option<i32> {
+        self.meanings.get(property).copied()
+    }
+
+    /// Set the meaning of a property.
+    pub fn set_meaning(&mut self, property: impl Into<String>, meaning: i32) {
+        self.meanings.insert(property.into(), meaning);
+    }
+}
+
+impl Index<&str> for Entity {
+    type Output = Value;
+
+    /// Get the value of a property by its path, or a null value if it is missing.
+    fn index(&self, path: &str) -> &Value {
+        path.split('.')
+            .fold(&self.properties, |current, name| &current[name])
+    }
 }
 
 /// Trait for converting a type to a Datastore entity (key + value).
@@ -71,17 +305,127 @@ where
     }
 }
 
+/// Trait for converting a Datastore entity (key + value) to a type.
+///
+/// It is implemented for every type implementing [`FromValue`], in which case the key is discarded.
+///
+/// Deriving `FromValue` for a struct with a `#[datastore(key)]` field only implements this trait,
+/// since such a struct needs the entity's key: it does not implement `FromValue`, so it can
+/// neither be nested within other values nor be converted from a lone value (or a `Vec` of them).
+pub trait FromEntity: Sized {
+    /// Attempts to construct a value of this type from the passed Datastore entity.
+    fn from_entity(entity: Entity) -> Result<Self, ConvertError>;
+}
+
+impl FromEntity for Entity {
+    fn from_entity(entity: Entity) -> Result<Entity, ConvertError> {
+        Ok(entity)
+    }
+}
+
+impl<V> FromEntity for (Key, V)
+where
+    V: FromValue,
+{
+    fn from_entity(entity: Entity) -> Result<(Key, V), ConvertError> {
+        let value = V::from_value(entity.properties)?;
+        Ok((entity.key, value))
+    }
+}
+
+impl<T> FromEntity for T
+where
+    T: FromValue,
+{
+    fn from_entity(entity: Entity) -> Result<T, ConvertError> {
+        T::from_value(entity.properties)
+    }
+}
+
 impl From<api::Entity> for Entity {
     fn from(entity: api::Entity) -> Entity {
         let key = Key::from(entity.key.unwrap());
-        let properties = entity.properties;
+        let mut excluded_from_indexes = HashSet::new();
+        let mut meanings = HashMap::new();
 
-        let properties = properties
+        let properties = entity
+            .properties
             .into_iter()
-            .map(|(k, v)| (k, Value::from(v.value_type.unwrap())))
+            .map(|(name, value)| {
+                let value =
+                    collect_property(&name, value, &mut excluded_from_indexes, &mut meanings);
+                (name, value)
+            })
             .collect();
         let properties = Value::EntityValue(properties);
 
-        Entity { key, properties }
+        Entity {
+            key,
+            properties,
+            excluded_from_indexes,
+            meanings,
+            version: None,
+        }
+    }
+}
+
+impl TryFrom<api::EntityResult> for Entity {
+    type Error = ConvertError;
+
+    fn try_from(result: api::EntityResult) -> Result<Entity, ConvertError> {
+        let entity = result
+            .entity
+            .ok_or_else(|| ConvertError::MissingProperty(String::from("entity")))?;
+        let mut entity = Entity::from(entity);
+        //? Versions are not set for eventually consistent reads and projections.
+        entity.version = Some(result.version).filter(|version| *version > 0);
+        Ok(entity)
+    }
+}
+
+fn not_an_entity(value: &Value) -> ConvertError {
+    ConvertError::UnexpectedPropertyType {
+        expected: String::from("entity"),
+        got: String::from(value.type_name()),
+    }
+}
+
+/// Converts a property's value, while recording its index exclusion and meaning under its path.
+fn collect_property(
+    path: &str,
+    value: api::Value,
+    excluded_from_indexes: &mut HashSet<String>,
+    meanings: &mut HashMap<String, i32>,
+) -> Value {
+    use api::value::ValueType;
+
+    if value.exclude_from_indexes {
+        excluded_from_indexes.insert(String::from(path));
+    }
+    if value.meaning != 0 {
+        meanings.insert(String::from(path), value.meaning);
+    }
+
+    match value.value_type.unwrap() {
+        ValueType::EntityValue(entity) => Value::EntityValue(
+            entity
+                .properties
+                .into_iter()
+                .map(|(name, value)| {
+                    let path = format!("{}.{}", path, name);
+                    let value = collect_property(&path, value, excluded_from_indexes, meanings);
+                    (name, value)
+                })
+                .collect(),
+        ),
+        //? Array elements share the path of the array itself.
+        ValueType::ArrayValue(array) => Value::ArrayValue(
+            array
+                .values
+                .into_iter()
+                .map(|value| collect_property(path, value, excluded_from_indexes, meanings))
+                .collect(),
+        ),
+        value_type => Value::from(value_type),
     }
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::datastore::{Cursor, IntoValue, ReadOptions, Value};
+
+/// Represents a value bound to a GQL query parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GqlBinding {
+    Value(Value),
+    Cursor(Cursor),
+}
+
+/// Represents a [GQL query](https://cloud.google.com/datastore/docs/reference/gql_reference).
+///
+/// Values can be bound to named (`@name`) or positional (`@1`) binding sites in the query string.
+///
+/// ```
+/// # use google_cloud::datastore::GqlQuery;
+/// let query = GqlQuery::new("SELECT * FROM users WHERE age > @age AND country = @1")
+///     .bind("age", 18)
+///     .bind_positional("France");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GqlQuery {
+    pub(crate) query_string: String,
+    pub(crate) allow_literals: bool,
+    pub(crate) read_options: ReadOptions,
+    pub(crate) namespace: Option<String>,
+    pub(crate) named_bindings: HashMap<String, GqlBinding>,
+    pub(crate) positional_bindings: Vec<GqlBinding>,
+}
+
+impl GqlQuery {
+    /// Construct a new GQL query from a query string.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users");
+    /// ```
+    pub fn new(query_string: impl Into<String>) -> GqlQuery {
+        GqlQuery {
+            query_string: query_string.into(),
+            allow_literals: true,
+            read_options: ReadOptions::default(),
+            namespace: None,
+            named_bindings: HashMap::new(),
+            positional_bindings: Vec::new(),
+        }
+    }
+
+    /// Ask to reject query strings containing literals, requiring all values to be bound instead.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users WHERE age > @age")
+    ///     .bind("age", 18)
+    ///     .disallow_literals();
+    /// ```
+    pub fn disallow_literals(mut self) -> GqlQuery {
+        self.allow_literals = false;
+        self
+    }
+
+    /// Ask to accept eventually consistent results.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users").eventually_consistent();
+    /// ```
+    pub fn eventually_consistent(mut self) -> GqlQuery {
+        self.read_options = ReadOptions::eventual();
+        self
+    }
+
+    /// Set the read options of the query, like its consistency.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{GqlQuery, ReadOptions};
+    /// let query = GqlQuery::new("SELECT * FROM users").read_options(ReadOptions::eventual());
+    /// ```
+    pub fn read_options(mut self, read_options: ReadOptions) -> GqlQuery {
+        self.read_options = read_options;
+        self
+    }
+
+    /// Associates the query with a namespace.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users").namespace("dev");
+    /// ```
+    pub fn namespace(mut self, namespace: impl Into<String>) -> GqlQuery {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Bind a value to a named binding site (`@name`).
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users WHERE name = @name").bind("name", "john");
+    /// ```
+    pub fn bind(mut self, name: impl Into<String>, value: impl IntoValue) -> GqlQuery {
+        let binding = GqlBinding::Value(value.into_value());
+        self.named_bindings.insert(name.into(), binding);
+        self
+    }
+
+    /// Bind a cursor to a named binding site (`@name`).
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// use google_cloud::datastore::Cursor;
+    ///
+    /// # fn run(cursor: Cursor) {
+    /// let query = GqlQuery::new("SELECT * FROM users LIMIT 10 OFFSET @start").bind_cursor("start", cursor);
+    /// # }
+    /// ```
+    pub fn bind_cursor(mut self, name: impl Into<String>, cursor: Cursor) -> GqlQuery {
+        let binding = GqlBinding::Cursor(cursor);
+        self.named_bindings.insert(name.into(), binding);
+        self
+    }
+
+    /// Bind a value to the next positional binding site (`@1`, then `@2`, and so on).
+    ///
+    /// ```
+    /// # use google_cloud::datastore::GqlQuery;
+    /// let query = GqlQuery::new("SELECT * FROM users WHERE age > @1 AND age < @2")
+    ///     .bind_positional(18)
+    ///     .bind_positional(65);
+    /// ```
+    pub fn bind_positional(mut self, value: impl IntoValue) -> GqlQuery {
+        let binding = GqlBinding::Value(value.into_value());
+        self.positional_bindings.push(binding);
+        self
+    }
+
+    /// Bind a cursor to the next positional binding site (`@1`, then `@2`, and so on).
+    pub fn bind_positional_cursor(mut self, cursor: Cursor) -> GqlQuery {
+        let binding = GqlBinding::Cursor(cursor);
+        self.positional_bindings.push(binding);
+        self
+    }
+}
+
+impl From<&str> for GqlQuery {
+    fn from(query_string: &str) -> GqlQuery {
+        GqlQuery::new(query_string)
+    }
+}
+
+impl From<String> for GqlQuery {
+    fn from(query_string: String) -> GqlQuery {
+        GqlQuery::new(query_string)
+    }
+}
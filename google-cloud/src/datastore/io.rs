@@ -0,0 +1,603 @@
+//! Each line holds a single entity, along with its key:
+//!
+//! ```json
+//! {"key":{"namespace":"dev","path":[{"kind":"users","id":10}]},"properties":{"name":"john","avatar":{"blob":"iVBORw0="}}}
+//! ```
+//!
+//! Strings, integers, booleans, nulls and arrays are stored as their JSON counterparts.
+//! Every other value is stored as a single-property object naming its type, which keeps the encoding lossless:
+//!
+//! - doubles: `{"double": 1.5}` (or `"NaN"`, `"Infinity"` and `"-Infinity"`)
+//! - timestamps: `{"timestamp": "2021-04-01T12:00:00.000000Z"}`
+//! - blobs: `{"blob": "<base64>"}`
+//! - keys: `{"key": {"namespace": "dev", "path": [{"kind": "users", "name": "john"}]}}`
+//! - geographical points: `{"geo_point": {"latitude": 48.85, "longitude": 2.35}}`
+//! - embedded entities: `{"entity": {...}}`
+//!
+//! Keys do not hold their project, entities are always imported into the project of the importer.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead};
+
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use futures::stream::{Stream, TryStreamExt};
+use json::{Map, Number};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::datastore::{
+    BulkWriteFailure, BulkWriteResult, BulkWriter, Entity, Error, Key, KeyID, Value,
+};
+use crate::error::ConvertError;
+
+type JsonValue = json::Value;
+
+/// Writes entities as newline-delimited JSON.
+///
+/// ```no_run
+/// # use google_cloud::datastore::{Client, Error, Query};
+/// use google_cloud::datastore::io::Exporter;
+/// use tokio::fs::File;
+/// use tokio::io::BufWriter;
+///
+/// # async fn run(client: Client) -> Result<(), Error> {
+/// let file = BufWriter::new(File::create("users.ndjson").await?);
+/// let mut exporter = Exporter::new(file);
+/// let count = exporter.export(client.query_stream(Query::new("users"))).await?;
+/// println!("exported {} entities", count);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Exporter<W> {
+    writer: W,
+}
+
+impl<W> Exporter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Creates a new exporter, writing into `writer`.
+    pub fn new(writer: W) -> Exporter<W> {
+        Exporter { writer }
+    }
+
+    /// Writes a single entity, as a line.
+    pub async fn write_entity(&mut self, entity: &Entity) -> io::Result<()> {
+        let mut line = json::to_vec(&encode_entity(entity))?;
+        line.push(b'\n');
+        self.writer.write_all(line.as_slice()).await
+    }
+
+    /// Writes all the entities of a stream (like the results of [`Client::query_stream`]),
+    /// and returns the number of written entities.
+    ///
+    /// [`Client::query_stream`]: crate::datastore::Client::query_stream
+    pub async fn export<S>(&mut self, mut entities: S) -> Result<usize, Error>
+    where
+        S: Stream<Item = Result<Entity, Error>> + Unpin,
+    {
+        let mut count = 0;
+        while let Some(entity) = entities.try_next().await? {
+            self.write_entity(&entity).await?;
+            count += 1;
+        }
+        self.writer.flush().await?;
+        Ok(count)
+    }
+
+    /// Get back the inner writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads entities from newline-delimited JSON, and writes them to Datastore using a [`BulkWriter`].
+///
+/// Entities can be moved into another project or namespace while being imported.
+///
+/// ```no_run
+/// # use google_cloud::datastore::{Client, Error};
+/// use google_cloud::datastore::io::Importer;
+/// use tokio::fs::File;
+/// use tokio::io::BufReader;
+///
+/// # async fn run(client: Client) -> Result<(), Error> {
+/// let file = BufReader::new(File::open("users.ndjson").await?);
+/// let importer = Importer::new(client.bulk_writer()).namespace("staging");
+/// let result = importer.import(file).await;
+/// println!("imported {} entities", result.written());
+/// if let Some(err) = result.error() {
+///     eprintln!("import stopped early: {}", err);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Importer {
+    writer: BulkWriter,
+    project_name: Option<String>,
+    namespace: Option<String>,
+}
+
+impl Importer {
+    /// Creates a new importer, writing entities with the given bulk writer.
+    pub fn new(writer: BulkWriter) -> Importer {
+        Importer {
+            writer,
+            project_name: None,
+            namespace: None,
+        }
+    }
+
+    /// Import the entities into another project than the one of the bulk writer's client.
+    pub fn project(mut self, project_name: impl Into<String>) -> Importer {
+        self.project_name = Some(project_name.into());
+        self
+    }
+
+    /// Import the entities, and the keys they reference, into the given namespace
+    /// (an empty string being the default namespace).
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Importer {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Reads all the entities from `reader` and writes them to Datastore.
+    ///
+    /// Entities are read and written in chunks, so the input never needs to fit in memory
+    /// (only the entities of failed commits are kept, to be reported).
+    /// Reading stops at the first malformed line (or input error): the entities read before it
+    /// are still written, and the error is reported along with the outcome of their writes.
+    pub async fn import<R>(&self, reader: R) -> ImportResult
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let project_name = match self.project_name.as_deref() {
+            Some(project_name) => project_name,
+            None => self.writer.client.project_name.as_str(),
+        };
+        let chunk_size = self.writer.max_mutations * self.writer.concurrency;
+        let mut result = ImportResult {
+            written: 0,
+            failures: Vec::new(),
+            error: None,
+        };
+        let mut chunk = Vec::with_capacity(chunk_size);
+        let mut lines = reader.lines();
+        let mut number = 0;
+        loop {
+            let entity = match lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => {
+                    number += 1;
+                    continue;
+                }
+                Ok(Some(line)) => {
+                    number += 1;
+                    decode_numbered_line(number, line.as_str())
+                }
+                Ok(None) => break,
+                Err(err) => Err(err),
+            };
+            let mut entity = match entity {
+                Ok(entity) => entity,
+                Err(err) => {
+                    result.error = Some(Error::from(err));
+                    break;
+                }
+            };
+            if let Some(namespace) = self.namespace.as_deref() {
+                remap_entity(&mut entity, namespace);
+            }
+            chunk.push(entity);
+            if chunk.len() >= chunk_size {
+                let written = self.writer.put_all_to(project_name, chunk.drain(..)).await;
+                if !result.record(written) {
+                    return result;
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            let written = self.writer.put_all_to(project_name, chunk).await;
+            result.record(written);
+        }
+        result
+    }
+}
+
+/// Represents the outcome of an import.
+///
+/// Written entities are only counted, while the failed commits are kept to be reported
+/// (along with their mutations, to retry them for instance).
+#[derive(Debug)]
+pub struct ImportResult {
+    written: usize,
+    failures: Vec<BulkWriteFailure>,
+    error: Option<Error>,
+}
+
+impl ImportResult {
+    /// Whether all the entities have been read and successfully written.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty() && self.error.is_none()
+    }
+
+    /// Get the number of entities successfully written.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Get the failed commits.
+    pub fn failures(&self) -> &[BulkWriteFailure] {
+        self.failures.as_slice()
+    }
+
+    /// Move the failed commits out.
+    pub fn into_failures(self) -> Vec<BulkWriteFailure> {
+        self.failures
+    }
+
+    /// Get the error that stopped the import early (like a malformed line), if any.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Records the outcome of a bulk write, returning whether the import can go on.
+    fn record(&mut self, written: Result<BulkWriteResult, ConvertError>) -> bool {
+        match written {
+            Ok(written) => {
+                self.written += written.written.len();
+                self.failures.extend(written.failures);
+                true
+            }
+            Err(err) => {
+                self.error = Some(Error::from(err));
+                false
+            }
+        }
+    }
+}
+
+/// Reads entities from newline-delimited JSON, lazily.
+///
+/// Blank lines are skipped, and malformed lines are reported as `InvalidData` errors,
+/// wrapping a [`ConvertError`] mentioning their line number.
+///
+/// ```
+/// # use google_cloud::datastore::{Entity, IntoValue, Key, Value};
+/// use google_cloud::datastore::io::{read_entities, Exporter};
+/// use std::collections::HashMap;
+///
+/// let mut properties = HashMap::new();
+/// properties.insert(String::from("avatar"), Value::BlobValue(vec![0, 1, 255]));
+/// properties.insert(String::from("score"), 1.0.into_value());
+/// properties.insert(String::from("location"), Value::GeoPointValue(48.85, 2.35));
+/// let key = Key::new("users").namespace("dev").id("john");
+/// let entity = Entity::new(key, properties).unwrap().exclude_from_indexes("avatar");
+///
+/// let mut exporter = Exporter::new(Vec::new());
+/// futures::executor::block_on(exporter.write_entity(&entity)).unwrap();
+/// let output = exporter.into_inner();
+///
+/// let entities: Vec<Entity> = read_entities(output.as_slice()).collect::<Result<_, _>>().unwrap();
+/// assert_eq!(entities, vec![entity]);
+/// ```
+pub fn read_entities<R>(reader: R) -> impl Iterator<Item = io::Result<Entity>>
+where
+    R: BufRead,
+{
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(index, line)| decode_numbered_line(index + 1, line?.as_str()))
+}
+
+fn decode_numbered_line(number: usize, line: &str) -> io::Result<Entity> {
+    decode_line(line).map_err(|err| {
+        let err = ConvertError::InvalidEncoding(format!("line {}: {}", number, err));
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    })
+}
+
+fn encode_entity(entity: &Entity) -> JsonValue {
+    let mut line = Map::new();
+    line.insert(String::from("key"), encode_key(&entity.key));
+    let properties = match &entity.properties {
+        Value::EntityValue(properties) => encode_properties(properties),
+        _ => JsonValue::Object(Map::new()),
+    };
+    line.insert(String::from("properties"), properties);
+    if !entity.excluded_from_indexes.is_empty() {
+        let mut excluded: Vec<&String> = entity.excluded_from_indexes.iter().collect();
+        excluded.sort();
+        let excluded = excluded.into_iter().cloned().map(JsonValue::String);
+        line.insert(
+            String::from("excluded_from_indexes"),
+            JsonValue::Array(excluded.collect()),
+        );
+    }
+    if !entity.meanings.is_empty() {
+        let meanings = entity
+            .meanings
+            .iter()
+            .map(|(path, meaning)| (path.clone(), JsonValue::from(*meaning)));
+        line.insert(
+            String::from("meanings"),
+            JsonValue::Object(meanings.collect()),
+        );
+    }
+    JsonValue::Object(line)
+}
+
+fn encode_key(key: &Key) -> JsonValue {
+    let mut path = Vec::new();
+    let mut current = Some(key);
+    while let Some(key) = current {
+        let mut element = Map::new();
+        element.insert(String::from("kind"), JsonValue::from(key.get_kind()));
+        match key.get_id() {
+            KeyID::IntID(id) => {
+                element.insert(String::from("id"), JsonValue::from(*id));
+            }
+            KeyID::StringID(name) => {
+                element.insert(String::from("name"), JsonValue::from(name.as_str()));
+            }
+            KeyID::Incomplete => {}
+        }
+        path.push(JsonValue::Object(element));
+        current = key.get_parent();
+    }
+    path.reverse();
+
+    let mut encoded = Map::new();
+    if let Some(namespace) = key.get_namespace() {
+        encoded.insert(String::from("namespace"), JsonValue::from(namespace));
+    }
+    encoded.insert(String::from("path"), JsonValue::Array(path));
+    JsonValue::Object(encoded)
+}
+
+fn encode_properties(properties: &HashMap<String, Value>) -> JsonValue {
+    let properties = properties
+        .iter()
+        .map(|(name, value)| (name.clone(), encode_value(value)));
+    JsonValue::Object(properties.collect())
+}
+
+fn encode_value(value: &Value) -> JsonValue {
+    let (tag, encoded) = match value {
+        Value::NullValue => return JsonValue::Null,
+        Value::BooleanValue(value) => return JsonValue::Bool(*value),
+        Value::IntegerValue(value) => return JsonValue::from(*value),
+        Value::StringValue(value) => return JsonValue::String(value.clone()),
+        Value::ArrayValue(values) => {
+            return JsonValue::Array(values.iter().map(encode_value).collect())
+        }
+        Value::DoubleValue(value) => {
+            let encoded = match Number::from_f64(*value) {
+                Some(number) => JsonValue::Number(number),
+                None if value.is_nan() => JsonValue::from("NaN"),
+                None if *value > 0.0 => JsonValue::from("Infinity"),
+                None => JsonValue::from("-Infinity"),
+            };
+            ("double", encoded)
+        }
+        Value::TimestampValue(value) => {
+            let encoded = value.to_rfc3339_opts(SecondsFormat::Micros, true);
+            ("timestamp", JsonValue::String(encoded))
+        }
+        Value::BlobValue(value) => ("blob", JsonValue::String(base64::encode(value))),
+        Value::KeyValue(key) => ("key", encode_key(key)),
+        Value::GeoPointValue(latitude, longitude) => {
+            let mut point = Map::new();
+            point.insert(String::from("latitude"), JsonValue::from(*latitude));
+            point.insert(String::from("longitude"), JsonValue::from(*longitude));
+            ("geo_point", JsonValue::Object(point))
+        }
+        Value::EntityValue(properties) => ("entity", encode_properties(properties)),
+    };
+    let mut tagged = Map::new();
+    tagged.insert(String::from(tag), encoded);
+    JsonValue::Object(tagged)
+}
+
+fn invalid(message: impl Into<String>) -> ConvertError {
+    ConvertError::InvalidEncoding(message.into())
+}
+
+fn decode_line(line: &str) -> Result<Entity, ConvertError> {
+    let line: JsonValue = json::from_str(line).map_err(|err| invalid(err.to_string()))?;
+    let mut line = match line {
+        JsonValue::Object(line) => line,
+        _ => return Err(invalid("expected an object")),
+    };
+    let key = match line.remove("key") {
+        Some(key) => decode_key(key)?,
+        None => return Err(ConvertError::MissingProperty(String::from("key"))),
+    };
+    let properties = match line.remove("properties") {
+        Some(JsonValue::Object(properties)) => decode_properties(properties)?,
+        Some(_) => return Err(invalid("expected `properties` to be an object")),
+        None => HashMap::new(),
+    };
+    let excluded_from_indexes = match line.remove("excluded_from_indexes") {
+        Some(JsonValue::Array(paths)) => paths
+            .into_iter()
+            .map(|path| match path {
+                JsonValue::String(path) => Ok(path),
+                _ => Err(invalid("expected excluded paths to be strings")),
+            })
+            .collect::<Result<HashSet<_>, _>>()?,
+        Some(_) => return Err(invalid("expected `excluded_from_indexes` to be an array")),
+        None => HashSet::new(),
+    };
+    let meanings = match line.remove("meanings") {
+        Some(JsonValue::Object(meanings)) => meanings
+            .into_iter()
+            .map(|(path, meaning)| match meaning.as_i64() {
+                Some(meaning) => Ok((path, meaning as i32)),
+                None => Err(invalid("expected meanings to be integers")),
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?,
+        Some(_) => return Err(invalid("expected `meanings` to be an object")),
+        None => HashMap::new(),
+    };
+
+    Ok(Entity {
+        key,
+        properties: Value::EntityValue(properties),
+        excluded_from_indexes,
+        meanings,
+        version: None,
+    })
+}
+
+fn decode_key(key: JsonValue) -> Result<Key, ConvertError> {
+    let mut key = match key {
+        JsonValue::Object(key) => key,
+        _ => return Err(invalid("expected a key object")),
+    };
+    let namespace = match key.remove("namespace") {
+        Some(JsonValue::String(namespace)) => Some(namespace),
+        Some(JsonValue::Null) | None => None,
+        Some(_) => return Err(invalid("expected the key's namespace to be a string")),
+    };
+    let path = match key.remove("path") {
+        Some(JsonValue::Array(path)) if !path.is_empty() => path,
+        _ => return Err(invalid("expected the key's path to be a non-empty array")),
+    };
+
+    let mut decoded: Option<Key> = None;
+    for element in path {
+        let mut element = match element {
+            JsonValue::Object(element) => element,
+            _ => return Err(invalid("expected key path elements to be objects")),
+        };
+        let kind = match element.remove("kind") {
+            Some(JsonValue::String(kind)) => kind,
+            _ => return Err(invalid("expected key path elements to have a kind")),
+        };
+        let id = match (element.remove("id"), element.remove("name")) {
+            (Some(id), None) => match id.as_i64() {
+                Some(id) => KeyID::IntID(id),
+                None => return Err(invalid("expected key IDs to be integers")),
+            },
+            (None, Some(JsonValue::String(name))) => KeyID::StringID(name),
+            (None, None) => KeyID::Incomplete,
+            _ => {
+                return Err(invalid(
+                    "expected key path elements to have either an ID or a name",
+                ))
+            }
+        };
+        let mut key = Key::new(kind).id(id);
+        key.namespace = namespace.clone();
+        if let Some(parent) = decoded {
+            key = key.parent(parent);
+        }
+        decoded = Some(key);
+    }
+
+    Ok(decoded.unwrap())
+}
+
+fn decode_properties(
+    properties: Map<String, JsonValue>,
+) -> Result<HashMap<String, Value>, ConvertError> {
+    properties
+        .into_iter()
+        .map(|(name, value)| Ok((name, decode_value(value)?)))
+        .collect()
+}
+
+fn decode_value(value: JsonValue) -> Result<Value, ConvertError> {
+    let mut tagged = match value {
+        JsonValue::Null => return Ok(Value::NullValue),
+        JsonValue::Bool(value) => return Ok(Value::BooleanValue(value)),
+        JsonValue::String(value) => return Ok(Value::StringValue(value)),
+        JsonValue::Number(number) => {
+            return match (number.as_i64(), number.as_f64()) {
+                (Some(value), _) => Ok(Value::IntegerValue(value)),
+                (None, Some(value)) if number.is_f64() => Ok(Value::DoubleValue(value)),
+                _ => Err(invalid(format!("integer out of range: {}", number))),
+            };
+        }
+        JsonValue::Array(values) => {
+            let values = values.into_iter().map(decode_value);
+            return Ok(Value::ArrayValue(values.collect::<Result<_, _>>()?));
+        }
+        JsonValue::Object(tagged) => tagged,
+    };
+    if tagged.len() != 1 {
+        return Err(invalid("expected tagged values to have a single property"));
+    }
+    let (tag, value) = tagged.iter_mut().next().unwrap();
+    let value = value.take();
+    match (tag.as_str(), value) {
+        ("double", JsonValue::Number(number)) => Ok(Value::DoubleValue(number.as_f64().unwrap())),
+        ("double", JsonValue::String(special)) => match special.as_str() {
+            "NaN" => Ok(Value::DoubleValue(f64::NAN)),
+            "Infinity" => Ok(Value::DoubleValue(f64::INFINITY)),
+            "-Infinity" => Ok(Value::DoubleValue(f64::NEG_INFINITY)),
+            _ => Err(invalid(format!("invalid double: {}", special))),
+        },
+        ("timestamp", JsonValue::String(timestamp)) => {
+            let timestamp = DateTime::parse_from_rfc3339(timestamp.as_str())
+                .map_err(|err| invalid(format!("invalid timestamp: {}", err)))?;
+            Ok(Value::TimestampValue(
+                timestamp.with_timezone(&Utc).trunc_subsecs(6),
+            ))
+        }
+        ("blob", JsonValue::String(blob)) => {
+            let blob = base64::decode(blob.as_str())
+                .map_err(|err| invalid(format!("invalid blob: {}", err)))?;
+            Ok(Value::BlobValue(blob))
+        }
+        ("key", key) => Ok(Value::KeyValue(decode_key(key)?)),
+        ("geo_point", JsonValue::Object(mut point)) => {
+            let latitude = point.remove("latitude").and_then(|value| value.as_f64());
+            let longitude = point.remove("longitude").and_then(|value| value.as_f64());
+            match (latitude, longitude) {
+                (Some(latitude), Some(longitude)) => Ok(Value::GeoPointValue(latitude, longitude)),
+                _ => Err(invalid(
+                    "expected geographical points to have a latitude and a longitude",
+                )),
+            }
+        }
+        ("entity", JsonValue::Object(properties)) => {
+            Ok(Value::EntityValue(decode_properties(properties)?))
+        }
+        (tag, _) => Err(invalid(format!("invalid value tagged as `{}`", tag))),
+    }
+}
+
+fn remap_entity(entity: &mut Entity, namespace: &str) {
+    remap_key(&mut entity.key, namespace);
+    remap_value(&mut entity.properties, namespace);
+}
+
+fn remap_key(key: &mut Key, namespace: &str) {
+    key.namespace = if namespace.is_empty() {
+        None
+    } else {
+        Some(String::from(namespace))
+    };
+    if let Some(parent) = key.parent.as_mut() {
+        remap_key(parent, namespace);
+    }
+}
+
+fn remap_value(value: &mut Value, namespace: &str) {
+    match value {
+        Value::KeyValue(key) => remap_key(key, namespace),
+        Value::ArrayValue(values) => {
+            for value in values {
+                remap_value(value, namespace);
+            }
+        }
+        Value::EntityValue(properties) => {
+            for value in properties.values_mut() {
+                remap_value(value, namespace);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,7 +1,18 @@
 use std::borrow::Borrow;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
 
 use crate::datastore::api;
 use crate::datastore::api::key::path_element::IdType;
+use crate::error::ConvertError;
+
+/// The newtype name under which keys are (de)serialized, which allows the conversions between
+/// Datastore values and `serde` types to map them to key values rather than to strings.
+pub(crate) const KEY_NEWTYPE_NAME: &str = "$google_cloud::datastore::Key";
 
 /// Represents a key's ID.
 ///
@@ -27,10 +38,7 @@ impl KeyID {
     /// assert!(!id2.is_incomplete());
     /// ```
     pub fn is_incomplete(&self) -> bool {
-        match self {
-            KeyID::Incomplete => true,
-            _ => false,
-        }
+        matches!(self, KeyID::Incomplete)
     }
 }
 
@@ -67,6 +75,53 @@ impl From<IdType> for KeyID {
 /// # use google_cloud::datastore::Key;
 /// let key = Key::new("kind").id("entity-name");
 /// ```
+///
+/// Keys can be turned into a stable, human-readable form (using `Display` and `FromStr`),
+/// made of the optional namespace followed by the path from the root ancestor.
+/// Kinds, namespaces and names are quoted unless they only contain ASCII alphanumeric
+/// characters, `_`, `-` or `.`, and incomplete keys have an empty ID.
+///
+/// A key's whole path belongs to a single partition, so just like when the key is sent to
+/// Datastore, only the namespace of the key itself is written, and parsed keys carry it on
+/// every ancestor. Keys whose ancestors were given different namespaces do not round-trip.
+///
+/// ```
+/// # use google_cloud::datastore::Key;
+/// let parent = Key::new("Kind").id(1).namespace("dev");
+/// let key = Key::new("Child").id("name").parent(parent).namespace("dev");
+/// assert_eq!(key.to_string(), r#"dev:Kind(1)/Child("name")"#);
+/// assert_eq!(key.to_string().parse::<Key>().unwrap(), key);
+/// assert_eq!(Key::new("My Kind").to_string(), r#""My Kind"()"#);
+/// ```
+///
+/// The project is not part of a key, so it is not part of this form either, but a
+/// project-qualified form (prefixed with `//<project>/`) is available through
+/// [`to_qualified_string`](Key::to_qualified_string) and
+/// [`parse_qualified`](Key::parse_qualified).
+///
+/// Keys implement `serde::Serialize` and `serde::Deserialize` using that same form, except when
+/// converted to and from Datastore values, where they are represented as key values.
+///
+/// ```
+/// # use google_cloud::datastore::{self, Key, Value};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Membership {
+///     group: Key,
+/// }
+///
+/// let membership = Membership { group: Key::new("groups").id(1) };
+/// let value = datastore::to_value(&membership).unwrap();
+/// match &value {
+///     Value::EntityValue(properties) => {
+///         assert_eq!(properties["group"], Value::KeyValue(Key::new("groups").id(1)));
+///     }
+///     _ => unreachable!(),
+/// }
+/// let membership: Membership = datastore::from_value(value).unwrap();
+/// assert_eq!(membership.group, Key::new("groups").id(1));
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Key {
     pub(crate) kind: String,
@@ -171,7 +226,7 @@ impl Key {
     /// assert_eq!(key2.get_namespace(), Some("dev"));
     /// ```
     pub fn get_namespace(&self) -> Option<&str> {
-        self.namespace.as_ref().map(|inner| inner.as_str())
+        self.namespace.as_deref()
     }
 
     /// Is the key incomplete (missing an ID) ?
@@ -186,6 +241,297 @@ impl Key {
     pub fn is_incomplete(&self) -> bool {
         self.get_id().is_incomplete()
     }
+
+    /// Encode the key, along with the specified project, in the project-qualified textual form.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Key;
+    /// let key = Key::new("Child").id("name").parent(Key::new("Kind").id(1)).namespace("dev");
+    /// assert_eq!(key.to_qualified_string("example"), r#"//example/dev:Kind(1)/Child("name")"#);
+    /// ```
+    pub fn to_qualified_string(&self, project_name: &str) -> String {
+        QualifiedKey(project_name, self).to_string()
+    }
+
+    /// Parse a key from the project-qualified textual form, along with the name of its project.
+    ///
+    /// This is the inverse of [`to_qualified_string`](Key::to_qualified_string).
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Key;
+    /// let (project, key) = Key::parse_qualified("//example/users(10)").unwrap();
+    /// assert_eq!(project, "example");
+    /// assert_eq!(key, Key::new("users").id(10));
+    /// assert!(Key::parse_qualified("users(10)").is_err());
+    /// ```
+    pub fn parse_qualified(text: &str) -> Result<(String, Key), ConvertError> {
+        let text = text
+            .strip_prefix("//")
+            .ok_or_else(|| invalid_key("expected `//` before the project"))?;
+        let mut chars = text.chars().peekable();
+        let project_name = parse_identifier(&mut chars)?;
+        expect_char(&mut chars, '/')?;
+        let key = parse_key(&mut chars)?;
+        Ok((project_name, key))
+    }
+
+    /// Encode the key as a URL-safe string, for the specified project.
+    ///
+    /// The encoding is compatible with the `urlsafe` keys of App Engine (and of the legacy
+    /// `ndb` and `db` libraries): a serialized `Reference` protocol buffer, in unpadded URL-safe
+    /// base64.
+    /// As with the textual form, only the namespace of the key itself is encoded.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Key;
+    /// let key = Key::new("Kind").id(1337);
+    /// assert_eq!(key.encode_urlsafe("example"), "agdleGFtcGxlcgsLEgRLaW5kGLkKDA");
+    /// ```
+    pub fn encode_urlsafe(&self, project_name: &str) -> String {
+        let mut path = Vec::new();
+        for key in self.ancestry() {
+            write_varint(&mut path, ELEMENT_START_TAG);
+            write_bytes(&mut path, ELEMENT_TYPE_TAG, key.kind.as_bytes());
+            match &key.id {
+                KeyID::IntID(id) => {
+                    write_varint(&mut path, ELEMENT_ID_TAG);
+                    write_varint(&mut path, *id as u64);
+                }
+                KeyID::StringID(name) => write_bytes(&mut path, ELEMENT_NAME_TAG, name.as_bytes()),
+                KeyID::Incomplete => {}
+            }
+            write_varint(&mut path, ELEMENT_END_TAG);
+        }
+
+        let mut reference = Vec::new();
+        write_bytes(&mut reference, REFERENCE_APP_TAG, project_name.as_bytes());
+        write_bytes(&mut reference, REFERENCE_PATH_TAG, path.as_slice());
+        if let Some(namespace) = self.namespace.as_deref().filter(|ns| !ns.is_empty()) {
+            write_bytes(
+                &mut reference,
+                REFERENCE_NAMESPACE_TAG,
+                namespace.as_bytes(),
+            );
+        }
+        base64::encode_config(reference.as_slice(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decode a key from a URL-safe string, along with the name of its project.
+    ///
+    /// This is the inverse of [`encode_urlsafe`](Key::encode_urlsafe).
+    /// The partition prefix App Engine adds to application IDs (like `s~`) is removed from the
+    /// project's name.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Key;
+    /// let (project, key) = Key::decode_urlsafe("aglzfmV4YW1wbGVyCwsSBEtpbmQYuQoM").unwrap();
+    /// assert_eq!(project, "example");
+    /// assert_eq!(key, Key::new("Kind").id(1337));
+    /// ```
+    pub fn decode_urlsafe(encoded: &str) -> Result<(String, Key), ConvertError> {
+        //? Encoders differ on whether padding is kept, so it is accepted but not required.
+        let bytes = base64::decode_config(encoded.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .map_err(|err| ConvertError::InvalidEncoding(err.to_string()))?;
+
+        let mut project_name = None;
+        let mut namespace = None;
+        let mut path = None;
+        let mut reader = ProtoReader::new(bytes.as_slice());
+        while let Some(tag) = reader.read_tag()? {
+            match tag {
+                REFERENCE_APP_TAG => project_name = Some(reader.read_string()?),
+                REFERENCE_NAMESPACE_TAG => namespace = Some(reader.read_string()?),
+                REFERENCE_PATH_TAG => path = Some(reader.read_bytes()?),
+                tag => reader.skip(tag)?,
+            }
+        }
+
+        let project_name = project_name.ok_or_else(|| invalid_reference("missing application"))?;
+        let project_name = match project_name.find('~') {
+            Some(index) => String::from(&project_name[index + 1..]),
+            None => project_name,
+        };
+        let namespace = namespace.filter(|namespace| !namespace.is_empty());
+
+        let mut key: Option<Key> = None;
+        let mut reader = ProtoReader::new(path.ok_or_else(|| invalid_reference("missing path"))?);
+        while let Some(tag) = reader.read_tag()? {
+            if tag != ELEMENT_START_TAG {
+                reader.skip(tag)?;
+                continue;
+            }
+            let mut kind = None;
+            let mut id = KeyID::Incomplete;
+            loop {
+                match reader.read_tag()? {
+                    Some(ELEMENT_END_TAG) => break,
+                    Some(ELEMENT_TYPE_TAG) => kind = Some(reader.read_string()?),
+                    Some(ELEMENT_ID_TAG) => id = KeyID::IntID(reader.read_varint()? as i64),
+                    Some(ELEMENT_NAME_TAG) => id = KeyID::StringID(reader.read_string()?),
+                    Some(tag) => reader.skip(tag)?,
+                    None => return Err(invalid_reference("unterminated path element")),
+                }
+            }
+            let kind = kind.ok_or_else(|| invalid_reference("missing kind"))?;
+            let element = Key {
+                kind,
+                id,
+                parent: key.take().map(Box::new),
+                namespace: namespace.clone(),
+            };
+            key = Some(element);
+        }
+
+        let key = key.ok_or_else(|| invalid_reference("empty path"))?;
+        Ok((project_name, key))
+    }
+
+    /// Get the key's path, from the root ancestor down to the key itself.
+    fn ancestry(&self) -> Vec<&Key> {
+        let mut path = Vec::new();
+        let mut current = Some(self);
+        while let Some(key) = current {
+            path.push(key);
+            current = key.get_parent();
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(namespace) = self.namespace.as_deref().filter(|ns| !ns.is_empty()) {
+            write_identifier(f, namespace)?;
+            f.write_str(":")?;
+        }
+        for (index, key) in self.ancestry().into_iter().enumerate() {
+            if index > 0 {
+                f.write_str("/")?;
+            }
+            write_identifier(f, key.kind.as_str())?;
+            match &key.id {
+                KeyID::IntID(id) => write!(f, "({})", id)?,
+                KeyID::StringID(name) => {
+                    f.write_str("(")?;
+                    write_quoted(f, name.as_str())?;
+                    f.write_str(")")?;
+                }
+                KeyID::Incomplete => f.write_str("()")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Key {
+    type Err = ConvertError;
+
+    fn from_str(text: &str) -> Result<Key, ConvertError> {
+        parse_key(&mut text.chars().peekable())
+    }
+}
+
+/// A key along with its project, displayed in the project-qualified textual form.
+struct QualifiedKey<'a>(&'a str, &'a Key);
+
+impl fmt::Display for QualifiedKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("//")?;
+        write_identifier(f, self.0)?;
+        write!(f, "/{}", self.1)
+    }
+}
+
+/// Parse the textual form of a key, until the end of the input.
+fn parse_key(chars: &mut Peekable<Chars>) -> Result<Key, ConvertError> {
+    let mut first = parse_identifier(chars)?;
+    let namespace = if chars.peek() == Some(&':') {
+        chars.next();
+        let namespace = first;
+        first = parse_identifier(chars)?;
+        Some(namespace).filter(|namespace| !namespace.is_empty())
+    } else {
+        None
+    };
+
+    let mut kind = first;
+    let mut key: Option<Key> = None;
+    loop {
+        expect_char(chars, '(')?;
+        let id = match chars.peek() {
+            Some(')') => KeyID::Incomplete,
+            Some('"') => KeyID::StringID(parse_quoted(chars)?),
+            _ => {
+                let mut digits = String::new();
+                while let Some(c) = chars.next_if(|c| *c == '-' || c.is_ascii_digit()) {
+                    digits.push(c);
+                }
+                let id = digits
+                    .parse()
+                    .map_err(|_| invalid_key(format!("invalid integer ID `{}`", digits)))?;
+                KeyID::IntID(id)
+            }
+        };
+        expect_char(chars, ')')?;
+        key = Some(Key {
+            kind,
+            id,
+            parent: key.take().map(Box::new),
+            namespace: namespace.clone(),
+        });
+
+        match chars.next() {
+            None => break,
+            Some('/') => kind = parse_identifier(chars)?,
+            Some(c) => return Err(invalid_key(format!("unexpected character `{}`", c))),
+        }
+    }
+
+    //? The loop always constructs at least one path element.
+    Ok(key.unwrap())
+}
+
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(KEY_NEWTYPE_NAME, self.to_string().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Key, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(KEY_NEWTYPE_NAME, KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Datastore key")
+    }
+
+    fn visit_str<E>(self, text: &str) -> Result<Key, E>
+    where
+        E: de::Error,
+    {
+        text.parse().map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Key, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
 }
 
 impl From<api::Key> for Key {
@@ -215,3 +561,188 @@ impl From<api::Key> for Key {
         key.unwrap()
     }
 }
+
+fn is_bare_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+fn write_identifier(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    if is_bare_identifier(text) {
+        f.write_str(text)
+    } else {
+        write_quoted(f, text)
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            f.write_str("\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\"")
+}
+
+fn invalid_key(message: impl fmt::Display) -> ConvertError {
+    ConvertError::InvalidEncoding(format!("invalid key: {}", message))
+}
+
+fn expect_char(chars: &mut Peekable<Chars>, expected: char) -> Result<(), ConvertError> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(invalid_key(format!(
+            "expected `{}`, found `{}`",
+            expected, c
+        ))),
+        None => Err(invalid_key(format!("expected `{}`, found end", expected))),
+    }
+}
+
+fn parse_identifier(chars: &mut Peekable<Chars>) -> Result<String, ConvertError> {
+    if chars.peek() == Some(&'"') {
+        return parse_quoted(chars);
+    }
+    let mut identifier = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || "_-.".contains(*c)) {
+        identifier.push(c);
+    }
+    if identifier.is_empty() {
+        return Err(invalid_key("expected a kind or a namespace"));
+    }
+    Ok(identifier)
+}
+
+fn parse_quoted(chars: &mut Peekable<Chars>) -> Result<String, ConvertError> {
+    expect_char(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some(c) if c == '"' || c == '\\' => text.push(c),
+                _ => return Err(invalid_key("invalid escape sequence")),
+            },
+            Some(c) => text.push(c),
+            None => return Err(invalid_key("unterminated string")),
+        }
+    }
+}
+
+//? Tags (field number and wire type) of the App Engine `Reference` protocol buffer:
+//?
+//? message Reference {
+//?   required string app = 13;
+//?   optional string name_space = 20;
+//?   required Path path = 14;
+//? }
+//? message Path {
+//?   repeated group Element = 1 {
+//?     required string type = 2;
+//?     optional int64 id = 3;
+//?     optional string name = 4;
+//?   }
+//? }
+const REFERENCE_APP_TAG: u64 = 13 << 3 | 2;
+const REFERENCE_PATH_TAG: u64 = 14 << 3 | 2;
+const REFERENCE_NAMESPACE_TAG: u64 = 20 << 3 | 2;
+const ELEMENT_START_TAG: u64 = 1 << 3 | 3;
+const ELEMENT_END_TAG: u64 = 1 << 3 | 4;
+const ELEMENT_TYPE_TAG: u64 = 2 << 3 | 2;
+const ELEMENT_ID_TAG: u64 = 3 << 3;
+const ELEMENT_NAME_TAG: u64 = 4 << 3 | 2;
+
+fn invalid_reference(message: &str) -> ConvertError {
+    ConvertError::InvalidEncoding(format!("invalid key reference: {}", message))
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_bytes(buffer: &mut Vec<u8>, tag: u64, bytes: &[u8]) {
+    write_varint(buffer, tag);
+    write_varint(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+struct ProtoReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ProtoReader<'a> {
+    fn new(bytes: &'a [u8]) -> ProtoReader<'a> {
+        ProtoReader { bytes }
+    }
+
+    fn read_tag(&mut self) -> Result<Option<u64>, ConvertError> {
+        if self.bytes.is_empty() {
+            Ok(None)
+        } else {
+            self.read_varint().map(Some)
+        }
+    }
+
+    fn read_varint(&mut self) -> Result<u64, ConvertError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = self
+                .bytes
+                .split_first()
+                .ok_or_else(|| invalid_reference("truncated varint"))?;
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_reference("varint overflow"))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], ConvertError> {
+        let len = self.read_varint()? as usize;
+        if len > self.bytes.len() {
+            return Err(invalid_reference("truncated field"));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self) -> Result<String, ConvertError> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid_reference("invalid UTF-8 string"))
+    }
+
+    /// Skip the value of an unknown field, based on the wire type of its tag.
+    fn skip(&mut self, tag: u64) -> Result<(), ConvertError> {
+        match tag & 0x7 {
+            0 => self.read_varint().map(drop),
+            1 | 5 => {
+                let len = if tag & 0x7 == 1 { 8 } else { 4 };
+                if len > self.bytes.len() {
+                    return Err(invalid_reference("truncated field"));
+                }
+                self.bytes = &self.bytes[len..];
+                Ok(())
+            }
+            2 => self.read_bytes().map(drop),
+            3 => loop {
+                match self.read_tag()? {
+                    Some(inner) if inner == (tag & !0x7 | 4) => return Ok(()),
+                    Some(inner) => self.skip(inner)?,
+                    None => return Err(invalid_reference("unterminated group")),
+                }
+            },
+            _ => Err(invalid_reference("unexpected wire type")),
+        }
+    }
+}
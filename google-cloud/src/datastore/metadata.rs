@@ -0,0 +1,133 @@
+use std::fmt;
+
+use crate::datastore::{Entity, FromEntity, KeyID, Value};
+use crate::error::ConvertError;
+
+/// Represents how values of a property are represented in Datastore indexes.
+///
+/// Multiple value types can share a representation (like timestamps and integers).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PropertyRepresentation {
+    /// Null values.
+    Null,
+    /// Integer and timestamp values.
+    Integer,
+    /// Boolean values.
+    Boolean,
+    /// String and blob values.
+    String,
+    /// Floating-point values.
+    Double,
+    /// Geographical point values.
+    Point,
+    /// Key values.
+    Reference,
+    /// A representation unknown to this library.
+    Other(String),
+}
+
+impl PropertyRepresentation {
+    /// Get the name of the representation, as reported by Datastore.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::PropertyRepresentation;
+    /// assert_eq!(PropertyRepresentation::Integer.as_str(), "INT64");
+    /// ```
+    pub fn as_str(&self) -> &str {
+        match self {
+            PropertyRepresentation::Null => "NULL",
+            PropertyRepresentation::Integer => "INT64",
+            PropertyRepresentation::Boolean => "BOOLEAN",
+            PropertyRepresentation::String => "STRING",
+            PropertyRepresentation::Double => "DOUBLE",
+            PropertyRepresentation::Point => "POINT",
+            PropertyRepresentation::Reference => "REFERENCE",
+            PropertyRepresentation::Other(name) => name.as_str(),
+        }
+    }
+}
+
+impl From<String> for PropertyRepresentation {
+    fn from(name: String) -> PropertyRepresentation {
+        match name.as_str() {
+            "NULL" => PropertyRepresentation::Null,
+            "INT64" => PropertyRepresentation::Integer,
+            "BOOLEAN" => PropertyRepresentation::Boolean,
+            "STRING" => PropertyRepresentation::String,
+            "DOUBLE" => PropertyRepresentation::Double,
+            "POINT" => PropertyRepresentation::Point,
+            "REFERENCE" => PropertyRepresentation::Reference,
+            _ => PropertyRepresentation::Other(name),
+        }
+    }
+}
+
+impl From<&str> for PropertyRepresentation {
+    fn from(name: &str) -> PropertyRepresentation {
+        PropertyRepresentation::from(String::from(name))
+    }
+}
+
+impl fmt::Display for PropertyRepresentation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Describes an indexed property of a kind, as returned by [`Client::properties`].
+///
+/// [`Client::properties`]: crate::datastore::Client::properties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyMetadata {
+    pub(crate) name: String,
+    pub(crate) representations: Vec<PropertyRepresentation>,
+}
+
+impl PropertyMetadata {
+    /// Get the name of the property (its dotted path, for properties of embedded entities).
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Get the representations the values of the property are stored with.
+    pub fn representations(&self) -> &[PropertyRepresentation] {
+        self.representations.as_slice()
+    }
+}
+
+impl FromEntity for PropertyMetadata {
+    fn from_entity(entity: Entity) -> Result<PropertyMetadata, ConvertError> {
+        let name = match entity.key().get_id() {
+            KeyID::StringID(name) => name.clone(),
+            _ => {
+                return Err(ConvertError::UnexpectedPropertyType {
+                    expected: String::from("string key"),
+                    got: String::from("integer key"),
+                })
+            }
+        };
+        let representations = match entity.into_properties() {
+            Value::EntityValue(mut props) => match props.remove("property_representation") {
+                Some(Value::ArrayValue(values)) => values,
+                Some(value) => vec![value],
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let representations = representations
+            .into_iter()
+            .map(|value| match value {
+                Value::StringValue(name) => Ok(PropertyRepresentation::from(name)),
+                value => Err(ConvertError::UnexpectedPropertyType {
+                    expected: String::from("string"),
+                    got: String::from(value.type_name()),
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(PropertyMetadata {
+            name,
+            representations,
+        })
+    }
+}
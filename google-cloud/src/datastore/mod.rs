@@ -1,9 +1,21 @@
+mod aggregation;
+mod bulk;
 mod client;
+mod de;
 mod entity;
+mod gql;
 mod key;
+mod metadata;
+mod model;
+mod mutation;
+mod options;
 mod query;
+mod repository;
+mod ser;
+mod transaction;
 mod value;
-mod api {
+#[allow(clippy::all)]
+pub(crate) mod api {
     pub mod r#type {
         include!("api/google.r#type.rs");
     }
@@ -16,10 +28,29 @@ mod api {
     pub use self::r#type::*;
 }
 
+/// Export and import of entities, as newline-delimited JSON.
+pub mod io;
+/// An in-memory fake of Datastore, to test code using the client without Google Cloud.
+#[cfg(feature = "datastore-testing")]
+//? The fake reports errors as gRPC statuses, as required by the generated service trait.
+#[allow(clippy::result_large_err)]
+pub mod testing;
+
+pub use self::aggregation::*;
+pub use self::bulk::*;
 pub use self::client::*;
+pub use self::de::*;
 pub use self::entity::*;
+pub use self::gql::*;
 pub use self::key::*;
+pub use self::metadata::*;
+pub use self::model::*;
+pub use self::mutation::*;
+pub use self::options::*;
 pub use self::query::*;
+pub use self::repository::*;
+pub use self::ser::*;
+pub use self::transaction::*;
 pub use self::value::*;
 
 /// The error type for the Datastore module.
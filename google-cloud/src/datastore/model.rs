@@ -0,0 +1,310 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::datastore::{Filter, IntoValue, Key, KeyID, Order};
+
+#[cfg(feature = "datastore-derive")]
+#[doc(hidden)]
+pub use google_cloud_derive::Model;
+
+/// Trait for types stored as entities of a fixed kind.
+///
+/// It can be derived, along with typed property paths for every field of the model.
+/// The kind defaults to the name of the type and the ID type defaults to `i64`.
+///
+/// ```
+/// # use google_cloud::datastore::{IntoValue, FromValue, Key, Model};
+/// #[derive(IntoValue, FromValue, Model)]
+/// #[datastore(kind = "users", namespace = "dev", id = "String")]
+/// struct User {
+///     #[datastore(key)]
+///     key: Key,
+///     name: String,
+///     age: i64,
+/// }
+///
+/// let key = User::key("john");
+/// assert_eq!(key.get_kind(), "users");
+/// assert_eq!(key.get_namespace(), Some("dev"));
+///
+/// let filter = User::AGE.greater_than(18);
+/// ```
+pub trait Model {
+    /// The kind of the model's entities.
+    const KIND: &'static str;
+    /// The namespace of the model's entities, if any.
+    const NAMESPACE: Option<&'static str> = None;
+    /// The type of the IDs of the model's keys (like `i64` or `String`).
+    type Id: Into<KeyID>;
+
+    /// Construct the key of the model's entity identified by `id`.
+    fn key(id: impl Into<Self::Id>) -> Key {
+        let key = Key::new(Self::KIND).id(id.into());
+        match Self::NAMESPACE {
+            Some(namespace) => key.namespace(namespace),
+            None => key,
+        }
+    }
+}
+
+/// A typed path to a property of the entities of `M`, which holds values of type `T`.
+///
+/// Properties are usually obtained from the constants generated when deriving [`Model`],
+/// and are used to build filters and orderings without repeating property names by hand.
+///
+/// ```
+/// # use google_cloud::datastore::{Model, Property};
+/// struct User;
+/// impl Model for User {
+///     const KIND: &'static str = "users";
+///     type Id = i64;
+/// }
+///
+/// struct Address;
+/// impl Model for Address {
+///     const KIND: &'static str = "addresses";
+///     type Id = i64;
+/// }
+///
+/// const ADDRESS: Property<User, Address> = Property::new("address");
+/// const CITY: Property<Address, String> = Property::new("city");
+///
+/// let path = ADDRESS.join(CITY);
+/// assert_eq!(path.name(), "address.city");
+/// let filter = path.equal("Paris");
+/// ```
+pub struct Property<M, T> {
+    name: Cow<'static, str>,
+    _marker: PhantomData<fn() -> (M, T)>,
+}
+
+impl<M, T> Property<M, T> {
+    /// Construct a property path from its name.
+    pub const fn new(name: &'static str) -> Property<M, T> {
+        Property {
+            name: Cow::Borrowed(name),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the name of the property (its full dotted path, for nested properties).
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    /// Extend the path with a property of the nested entity.
+    pub fn join<U>(&self, property: Property<T, U>) -> Property<M, U> {
+        Property {
+            name: Cow::Owned(format!("{}.{}", self.name, property.name)),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Order results by ascending values of the property.
+    pub fn asc(&self) -> ModelOrder<M> {
+        ModelOrder::new(Order::Asc(self.name().to_string()))
+    }
+
+    /// Order results by descending values of the property.
+    pub fn desc(&self) -> ModelOrder<M> {
+        ModelOrder::new(Order::Desc(self.name().to_string()))
+    }
+}
+
+impl<M, T> Property<M, T>
+where
+    T: IntoValue,
+{
+    /// Filter entities whose property is equal to `value`.
+    pub fn equal(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::Equal(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is not equal to `value`.
+    pub fn not_equal(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::NotEqual(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is greater than `value`.
+    pub fn greater_than(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::GreaterThan(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is greater than or equal to `value`.
+    pub fn greater_than_or_equal(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::GreaterThanOrEqual(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is lesser than `value`.
+    pub fn lesser_than(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::LesserThan(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is lesser than or equal to `value`.
+    pub fn lesser_than_or_equal(&self, value: impl Into<T>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::LesserThanEqual(
+            self.name().to_string(),
+            value.into().into_value(),
+        ))
+    }
+
+    /// Filter entities whose property is equal to any of the `values`.
+    pub fn one_of<I>(&self, values: I) -> ModelFilter<M>
+    where
+        I: IntoIterator,
+        I::Item: Into<T>,
+    {
+        let values = values.into_iter().map(|value| value.into().into_value());
+        ModelFilter::new(Filter::In(self.name().to_string(), values.collect()))
+    }
+
+    /// Filter entities whose property is equal to none of the `values`.
+    pub fn none_of<I>(&self, values: I) -> ModelFilter<M>
+    where
+        I: IntoIterator,
+        I::Item: Into<T>,
+    {
+        let values = values.into_iter().map(|value| value.into().into_value());
+        ModelFilter::new(Filter::NotIn(self.name().to_string(), values.collect()))
+    }
+}
+
+impl<M, T> Clone for Property<M, T> {
+    fn clone(&self) -> Property<M, T> {
+        Property {
+            name: self.name.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M, T> fmt::Debug for Property<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Property").field(&self.name).finish()
+    }
+}
+
+impl<M, T> fmt::Display for Property<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A filter over the entities of `M`, built from the model's [`Property`] paths.
+///
+/// Model queries only accept filters of their own model,
+/// so that filters on the properties of another kind are caught at compile time.
+pub struct ModelFilter<M> {
+    filter: Filter,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M> ModelFilter<M> {
+    /// Wrap an untyped filter, assuming it applies to the entities of `M`.
+    pub fn new(filter: Filter) -> ModelFilter<M> {
+        ModelFilter {
+            filter,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Combine with another filter, matching if both filters match.
+    pub fn and(self, other: ModelFilter<M>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::And(vec![self.filter, other.filter]))
+    }
+
+    /// Combine with another filter, matching if any of the filters match.
+    pub fn or(self, other: ModelFilter<M>) -> ModelFilter<M> {
+        ModelFilter::new(Filter::Or(vec![self.filter, other.filter]))
+    }
+
+    /// Get the underlying untyped filter.
+    pub fn into_filter(self) -> Filter {
+        self.filter
+    }
+}
+
+impl<M> From<ModelFilter<M>> for Filter {
+    fn from(filter: ModelFilter<M>) -> Filter {
+        filter.filter
+    }
+}
+
+impl<M> Clone for ModelFilter<M> {
+    fn clone(&self) -> ModelFilter<M> {
+        ModelFilter::new(self.filter.clone())
+    }
+}
+
+impl<M> PartialEq for ModelFilter<M> {
+    fn eq(&self, other: &ModelFilter<M>) -> bool {
+        self.filter == other.filter
+    }
+}
+
+impl<M> fmt::Debug for ModelFilter<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ModelFilter").field(&self.filter).finish()
+    }
+}
+
+/// An ordering of the entities of `M`, built from the model's [`Property`] paths.
+pub struct ModelOrder<M> {
+    order: Order,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M> ModelOrder<M> {
+    /// Wrap an untyped ordering, assuming it applies to the entities of `M`.
+    pub fn new(order: Order) -> ModelOrder<M> {
+        ModelOrder {
+            order,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the underlying untyped ordering.
+    pub fn into_order(self) -> Order {
+        self.order
+    }
+}
+
+impl<M> From<ModelOrder<M>> for Order {
+    fn from(order: ModelOrder<M>) -> Order {
+        order.order
+    }
+}
+
+impl<M> Clone for ModelOrder<M> {
+    fn clone(&self) -> ModelOrder<M> {
+        ModelOrder::new(self.order.clone())
+    }
+}
+
+impl<M> PartialEq for ModelOrder<M> {
+    fn eq(&self, other: &ModelOrder<M>) -> bool {
+        self.order == other.order
+    }
+}
+
+impl<M> fmt::Debug for ModelOrder<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ModelOrder").field(&self.order).finish()
+    }
+}
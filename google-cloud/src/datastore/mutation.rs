@@ -1,13 +1,144 @@
 use crate::datastore::api;
-use crate::datastore::{Key, Value};
+use crate::datastore::{Entity, IntoEntity, Key};
+use crate::error::ConvertError;
 
 /// Represents a Datastore mutation operation.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mutation {
+    /// Inserts an entity, which must not already exist.
+    /// If the entity's key is incomplete, one will be generated by the store.
     Insert(Entity),
+    /// Updates an entity, which must already exist.
     Update(Entity),
+    /// Inserts an entity, or replaces it if it already exists.
+    /// If the entity's key is incomplete, one will be generated by the store.
     Upsert(Entity),
+    /// Deletes the entity identified by a key, whether it exists or not.
     Delete(Key),
+    /// Applies the inner mutation only if the entity's current version matches the base version.
+    ///
+    /// Otherwise, the mutation is not applied and its result reports the conflict.
+    Conditional {
+        /// The mutation to apply.
+        mutation: Box<Mutation>,
+        /// The version the entity is expected to have.
+        base_version: i64,
+    },
 }
 
-impl Mutation {}
+impl Mutation {
+    /// Constructs an insert mutation.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Key, Mutation};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut props = HashMap::new();
+    /// props.insert(String::from("name"), "john");
+    /// let mutation = Mutation::insert((Key::new("users").id("john"), props));
+    /// assert!(mutation.is_ok());
+    /// ```
+    pub fn insert(entity: impl IntoEntity) -> Result<Mutation, ConvertError> {
+        Ok(Mutation::Insert(entity.into_entity()?))
+    }
+
+    /// Constructs an update mutation.
+    pub fn update(entity: impl IntoEntity) -> Result<Mutation, ConvertError> {
+        Ok(Mutation::Update(entity.into_entity()?))
+    }
+
+    /// Constructs an upsert mutation.
+    pub fn upsert(entity: impl IntoEntity) -> Result<Mutation, ConvertError> {
+        Ok(Mutation::Upsert(entity.into_entity()?))
+    }
+
+    /// Constructs a delete mutation.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Key, Mutation};
+    /// let key = Key::new("users").id("john");
+    /// let mutation = Mutation::delete(key.clone());
+    /// assert_eq!(mutation.key(), &key);
+    /// ```
+    pub fn delete(key: Key) -> Mutation {
+        Mutation::Delete(key)
+    }
+
+    /// Make the mutation conditional, only applying it if the entity's current version matches the base version.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Key, Mutation};
+    /// let key = Key::new("users").id("john");
+    /// let mutation = Mutation::delete(key).base_version(42);
+    /// assert_eq!(mutation.get_base_version(), Some(42));
+    /// ```
+    pub fn base_version(self, base_version: i64) -> Mutation {
+        let mutation = match self {
+            Mutation::Conditional { mutation, .. } => mutation,
+            mutation => Box::new(mutation),
+        };
+        Mutation::Conditional {
+            mutation,
+            base_version,
+        }
+    }
+
+    /// Get the base version of the mutation, if it is conditional.
+    pub fn get_base_version(&self) -> Option<i64> {
+        match self {
+            Mutation::Conditional { base_version, .. } => Some(*base_version),
+            _ => None,
+        }
+    }
+
+    /// Get the key of the entity targeted by the mutation.
+    pub fn key(&self) -> &Key {
+        match self {
+            Mutation::Conditional { mutation, .. } => mutation.key(),
+            Mutation::Insert(entity) | Mutation::Update(entity) | Mutation::Upsert(entity) => {
+                entity.key()
+            }
+            Mutation::Delete(key) => key,
+        }
+    }
+}
+
+/// Represents the result of applying a mutation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationResult {
+    pub(crate) key: Option<Key>,
+    pub(crate) version: i64,
+    pub(crate) conflict_detected: bool,
+}
+
+impl MutationResult {
+    /// Get the key generated by the store, if the mutation's key was incomplete.
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    /// Move the generated key out, if any.
+    pub fn into_key(self) -> Option<Key> {
+        self.key
+    }
+
+    /// Get the version of the entity on the server after the mutation was processed.
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Was a conflict detected for this mutation ?
+    pub fn conflict_detected(&self) -> bool {
+        self.conflict_detected
+    }
+}
+
+impl From<api::MutationResult> for MutationResult {
+    fn from(result: api::MutationResult) -> MutationResult {
+        MutationResult {
+            key: result.key.map(Key::from),
+            version: result.version,
+            conflict_detected: result.conflict_detected,
+        }
+    }
+}
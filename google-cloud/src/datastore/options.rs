@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+
+use crate::datastore::Transaction;
+
+/// Represents the options of Datastore reads (lookups and queries).
+///
+/// By default, reads are strongly consistent.
+///
+/// ```
+/// # use google_cloud::datastore::ReadOptions;
+/// let options = ReadOptions::eventual();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReadOptions {
+    pub(crate) consistency: ReadConsistency,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) enum ReadConsistency {
+    #[default]
+    Strong,
+    Eventual,
+    Transaction(Vec<u8>),
+    ReadTime(DateTime<Utc>),
+}
+
+impl ReadOptions {
+    /// Read the most up-to-date data.
+    pub fn strong() -> ReadOptions {
+        ReadOptions {
+            consistency: ReadConsistency::Strong,
+        }
+    }
+
+    /// Accept possibly stale data, which makes reads cheaper and faster.
+    pub fn eventual() -> ReadOptions {
+        ReadOptions {
+            consistency: ReadConsistency::Eventual,
+        }
+    }
+
+    /// Read from the consistent snapshot of a transaction.
+    pub fn transaction(transaction: &Transaction) -> ReadOptions {
+        ReadOptions {
+            consistency: ReadConsistency::Transaction(transaction.id.clone()),
+        }
+    }
+
+    /// Read data as it was at a point in time.
+    ///
+    /// This is only supported by Firestore in Datastore mode, and the time may not be older than 270 seconds.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::ReadOptions;
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let options = ReadOptions::read_time(Utc::now() - Duration::seconds(60));
+    /// ```
+    pub fn read_time(time: DateTime<Utc>) -> ReadOptions {
+        ReadOptions {
+            consistency: ReadConsistency::ReadTime(time),
+        }
+    }
+
+    /// Whether these options accept possibly stale data.
+    pub fn is_eventual(&self) -> bool {
+        matches!(self.consistency, ReadConsistency::Eventual)
+    }
+}
@@ -1,4 +1,10 @@
-use crate::datastore::{Key, Value};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::datastore::api;
+use crate::datastore::{Entity, Key, ReadOptions, Value};
+use crate::error::ConvertError;
 
 /// Represents Datastore query result orderings.
 #[derive(Debug, Clone, PartialEq)]
@@ -9,11 +15,29 @@ pub enum Order {
     Desc(String),
 }
 
-/// Represents Datastore query result orderings.
+/// Represents Datastore query filters.
+///
+/// Filters can be combined into trees, using `Filter::And` and `Filter::Or`.
+///
+/// ```
+/// # use google_cloud::datastore::Filter;
+/// use google_cloud::datastore::IntoValue;
+///
+/// // status IN ("open", "pending") AND (owner = "john" OR shared = true)
+/// let filter = Filter::And(vec![
+///     Filter::In("status".into(), vec!["open".into_value(), "pending".into_value()]),
+///     Filter::Or(vec![
+///         Filter::Equal("owner".into(), "john".into_value()),
+///         Filter::Equal("shared".into(), true.into_value()),
+///     ]),
+/// ]);
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub enum Filter {
     /// Equality filter (==).
     Equal(String, Value),
+    /// Inequality filter (!=).
+    NotEqual(String, Value),
     /// Greater-than filter (>).
     GreaterThan(String, Value),
     /// Lesser-than filter (<).
@@ -22,13 +46,23 @@ pub enum Filter {
     GreaterThanOrEqual(String, Value),
     /// Lesser-than-or-equal filter (<=).
     LesserThanEqual(String, Value),
+    /// Membership filter (IN), matches if the property is equal to any of the values.
+    In(String, Vec<Value>),
+    /// Non-membership filter (NOT IN), matches if the property is equal to none of the values.
+    NotIn(String, Vec<Value>),
+    /// Ancestor filter, matches the entity identified by the key and its descendants.
+    HasAncestor(Key),
+    /// Conjunction filter, matches if all of the inner filters match.
+    And(Vec<Filter>),
+    /// Disjunction filter, matches if any of the inner filters match.
+    Or(Vec<Filter>),
 }
 
 /// Represents a Datastore query.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Query {
     pub(crate) kind: String,
-    pub(crate) eventual: bool,
+    pub(crate) read_options: ReadOptions,
     pub(crate) keys_only: bool,
     pub(crate) offset: i32,
     pub(crate) limit: Option<i32>,
@@ -38,6 +72,8 @@ pub struct Query {
     pub(crate) distinct_on: Vec<String>,
     pub(crate) ordering: Vec<Order>,
     pub(crate) filters: Vec<Filter>,
+    pub(crate) start_cursor: Option<Cursor>,
+    pub(crate) end_cursor: Option<Cursor>,
 }
 
 impl Query {
@@ -50,7 +86,7 @@ impl Query {
     pub fn new(kind: impl Into<String>) -> Query {
         Query {
             kind: kind.into(),
-            eventual: false,
+            read_options: ReadOptions::default(),
             keys_only: false,
             offset: 0,
             limit: None,
@@ -60,6 +96,8 @@ impl Query {
             distinct_on: Vec::new(),
             ordering: Vec::new(),
             filters: Vec::new(),
+            start_cursor: None,
+            end_cursor: None,
         }
     }
 
@@ -72,7 +110,18 @@ impl Query {
     ///     .eventually_consistent();
     /// ```
     pub fn eventually_consistent(mut self) -> Query {
-        self.eventual = true;
+        self.read_options = ReadOptions::eventual();
+        self
+    }
+
+    /// Set the read options of the query, like its consistency.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{Query, ReadOptions};
+    /// let query = Query::new("users").read_options(ReadOptions::eventual());
+    /// ```
+    pub fn read_options(mut self, read_options: ReadOptions) -> Query {
+        self.read_options = read_options;
         self
     }
 
@@ -175,7 +224,7 @@ impl Query {
     }
 
     /// Filter results based on their fields.
-    /// Multiple filters are combined with an 'AND'.
+    /// Multiple filters are combined with an 'AND' (use `Filter::Or` for disjunctions).
     ///
     /// ```
     /// # use google_cloud::datastore::Query;
@@ -205,4 +254,148 @@ impl Query {
         self.ordering.push(order);
         self
     }
+
+    /// Start yielding results from the position pointed by a cursor.
+    /// The cursor must come from the results of the same query.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Query;
+    /// use google_cloud::datastore::Cursor;
+    ///
+    /// # fn run(cursor: Cursor) {
+    /// let query = Query::new("users").start_cursor(cursor).limit(25);
+    /// # }
+    /// ```
+    pub fn start_cursor(mut self, cursor: Cursor) -> Query {
+        self.start_cursor = Some(cursor);
+        self
+    }
+
+    /// Stop yielding results at the position pointed by a cursor.
+    /// The cursor must come from the results of the same query.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::Query;
+    /// use google_cloud::datastore::Cursor;
+    ///
+    /// # fn run(cursor: Cursor) {
+    /// let query = Query::new("users").end_cursor(cursor);
+    /// # }
+    /// ```
+    pub fn end_cursor(mut self, cursor: Cursor) -> Query {
+        self.end_cursor = Some(cursor);
+        self
+    }
+}
+
+/// Represents an opaque position within the results of a query.
+///
+/// Cursors can be turned into URL-safe strings (using `Display` and `FromStr`),
+/// to resume a query at a later time, like when paginating through results.
+///
+/// ```
+/// # use google_cloud::datastore::Cursor;
+/// let cursor = Cursor::from_bytes(vec![1, 2, 3]);
+/// let encoded = cursor.to_string();
+/// let decoded: Cursor = encoded.parse().unwrap();
+/// assert_eq!(cursor, decoded);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor(pub(crate) Vec<u8>);
+
+impl Cursor {
+    /// Construct a cursor from its raw bytes.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Cursor {
+        Cursor(bytes.into())
+    }
+
+    /// Get the raw bytes of the cursor.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    /// Move the raw bytes of the cursor out.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoded = base64::encode_config(self.0.as_slice(), base64::URL_SAFE_NO_PAD);
+        f.write_str(encoded.as_str())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = ConvertError;
+
+    fn from_str(encoded: &str) -> Result<Cursor, ConvertError> {
+        let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .map_err(|err| ConvertError::InvalidEncoding(err.to_string()))?;
+        Ok(Cursor(bytes))
+    }
+}
+
+/// Represents a batch of query results, as returned by Datastore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryBatch {
+    pub(crate) entities: Vec<Entity>,
+    pub(crate) cursors: Vec<Cursor>,
+    pub(crate) end_cursor: Cursor,
+    pub(crate) more_results: bool,
+}
+
+impl QueryBatch {
+    /// Get the entities of this batch.
+    pub fn entities(&self) -> &[Entity] {
+        self.entities.as_slice()
+    }
+
+    /// Move the entities of this batch out.
+    pub fn into_entities(self) -> Vec<Entity> {
+        self.entities
+    }
+
+    /// Get the cursor pointing right after the entity at the given index in this batch, if any.
+    ///
+    /// Using it as a start cursor resumes the query after that entity.
+    pub fn cursor_after(&self, index: usize) -> Option<&Cursor> {
+        self.cursors.get(index)
+    }
+
+    /// Get the cursor pointing right after the last result of this batch.
+    ///
+    /// Using it as a start cursor resumes the query after this batch.
+    pub fn end_cursor(&self) -> &Cursor {
+        &self.end_cursor
+    }
+
+    /// Can there be more results after this batch ?
+    ///
+    /// This is also true when the query is finished because of a limit or an end cursor,
+    /// but there are more results past it.
+    pub fn has_more_results(&self) -> bool {
+        self.more_results
+    }
+}
+
+impl TryFrom<api::QueryResultBatch> for QueryBatch {
+    type Error = ConvertError;
+
+    fn try_from(batch: api::QueryResultBatch) -> Result<QueryBatch, ConvertError> {
+        use api::query_result_batch::MoreResultsType;
+        let mut entities = Vec::with_capacity(batch.entity_results.len());
+        let mut cursors = Vec::with_capacity(batch.entity_results.len());
+        for mut result in batch.entity_results {
+            cursors.push(Cursor(std::mem::take(&mut result.cursor)));
+            entities.push(Entity::try_from(result)?);
+        }
+        Ok(QueryBatch {
+            entities,
+            cursors,
+            end_cursor: Cursor(batch.end_cursor),
+            more_results: batch.more_results != (MoreResultsType::NoMoreResults as i32),
+        })
+    }
 }
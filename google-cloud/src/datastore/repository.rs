@@ -0,0 +1,249 @@
+use std::marker::PhantomData;
+
+use futures::future;
+use futures::stream::{Stream, TryStreamExt};
+
+use crate::datastore::{
+    Client, Cursor, Error, FromEntity, IntoEntity, Key, Model, ModelFilter, ModelOrder, Query,
+    ReadOptions,
+};
+
+/// Typed access to the entities of a [`Model`].
+///
+/// Keys are built from the model's IDs, and queries are bound to the model's kind and namespace.
+///
+/// ```no_run
+/// # use google_cloud::datastore::{Client, Error, IntoValue, FromValue, Key, Model};
+/// #[derive(IntoValue, FromValue, Model)]
+/// #[datastore(kind = "users")]
+/// struct User {
+///     #[datastore(key)]
+///     key: Key,
+///     name: String,
+///     age: i64,
+/// }
+///
+/// # async fn run(client: Client) -> Result<(), Error> {
+/// let mut users = client.repository::<User>();
+/// let user: Option<User> = users.get(10).await?;
+///
+/// let adults: Vec<User> = users
+///     .query()
+///     .filter(User::AGE.greater_than_or_equal(18))
+///     .order(User::NAME.asc())
+///     .fetch()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Repository<M> {
+    pub(crate) client: Client,
+    _model: PhantomData<fn() -> M>,
+}
+
+impl<M> Repository<M>
+where
+    M: Model,
+{
+    /// Creates a new repository for the entities of `M`.
+    pub fn new(client: Client) -> Repository<M> {
+        Repository {
+            client,
+            _model: PhantomData,
+        }
+    }
+
+    /// Gets the model identified by `id` from Datastore.
+    pub async fn get(&mut self, id: impl Into<M::Id>) -> Result<Option<M>, Error>
+    where
+        M: FromEntity,
+    {
+        self.client.get(M::key(id)).await
+    }
+
+    /// Gets multiple models from Datastore, skipping the missing ones.
+    pub async fn get_all<I>(&mut self, ids: I) -> Result<Vec<M>, Error>
+    where
+        M: FromEntity,
+        I: IntoIterator,
+        I::Item: Into<M::Id>,
+    {
+        let keys: Vec<Key> = ids.into_iter().map(M::key).collect();
+        self.client.get_all(keys).await
+    }
+
+    /// Inserts or updates a model in Datastore.
+    ///
+    /// Returns the allocated key, if the model's key was incomplete.
+    pub async fn put(&mut self, model: M) -> Result<Option<Key>, Error>
+    where
+        M: IntoEntity,
+    {
+        self.client.put(model).await
+    }
+
+    /// Inserts or updates multiple models in Datastore.
+    pub async fn put_all<I>(&mut self, models: I) -> Result<Vec<Option<Key>>, Error>
+    where
+        M: IntoEntity,
+        I: IntoIterator<Item = M>,
+    {
+        self.client.put_all(models).await
+    }
+
+    /// Deletes the model identified by `id` from Datastore.
+    pub async fn delete(&mut self, id: impl Into<M::Id>) -> Result<(), Error> {
+        self.client.delete(M::key(id)).await
+    }
+
+    /// Deletes multiple models from Datastore.
+    pub async fn delete_all<I>(&mut self, ids: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<M::Id>,
+    {
+        let keys: Vec<Key> = ids.into_iter().map(M::key).collect();
+        self.client.delete_all(keys).await
+    }
+
+    /// Starts a query over the entities of `M`.
+    pub fn query(&self) -> ModelQuery<M> {
+        let query = Query::new(M::KIND);
+        let query = match M::NAMESPACE {
+            Some(namespace) => query.namespace(namespace),
+            None => query,
+        };
+        ModelQuery {
+            client: self.client.clone(),
+            query,
+            _model: PhantomData,
+        }
+    }
+}
+
+impl<M> Clone for Repository<M> {
+    fn clone(&self) -> Repository<M> {
+        Repository {
+            client: self.client.clone(),
+            _model: PhantomData,
+        }
+    }
+}
+
+/// A query over the entities of a [`Model`], obtained from [`Repository::query`].
+///
+/// Only filters and orderings built from the properties of the same model are accepted:
+///
+/// ```compile_fail
+/// # use google_cloud::datastore::{Client, Model, Property};
+/// struct User;
+/// impl Model for User {
+///     const KIND: &'static str = "users";
+///     type Id = i64;
+/// }
+///
+/// struct Post;
+/// impl Model for Post {
+///     const KIND: &'static str = "posts";
+///     type Id = i64;
+/// }
+///
+/// const TITLE: Property<Post, String> = Property::new("title");
+///
+/// # fn run(client: Client) {
+/// let query = client.repository::<User>().query().filter(TITLE.equal("hello"));
+/// # }
+/// ```
+pub struct ModelQuery<M> {
+    pub(crate) client: Client,
+    pub(crate) query: Query,
+    _model: PhantomData<fn() -> M>,
+}
+
+impl<M> ModelQuery<M>
+where
+    M: Model,
+{
+    /// Filter results based on the properties of the model (see [`Query::filter`]).
+    ///
+    /// Untyped filters can still be applied to the query returned by [`ModelQuery::into_query`].
+    pub fn filter(mut self, filter: ModelFilter<M>) -> ModelQuery<M> {
+        self.query = self.query.filter(filter.into_filter());
+        self
+    }
+
+    /// Order results based on the properties of the model (see [`Query::order`]).
+    pub fn order(mut self, order: ModelOrder<M>) -> ModelQuery<M> {
+        self.query = self.query.order(order.into_order());
+        self
+    }
+
+    /// Skip any number of results (see [`Query::offset`]).
+    pub fn offset(mut self, offset: i32) -> ModelQuery<M> {
+        self.query = self.query.offset(offset);
+        self
+    }
+
+    /// Limit the number of results (see [`Query::limit`]).
+    pub fn limit(mut self, limit: i32) -> ModelQuery<M> {
+        self.query = self.query.limit(limit);
+        self
+    }
+
+    /// Only yield the descendants of an entity (see [`Query::ancestor`]).
+    pub fn ancestor(mut self, key: Key) -> ModelQuery<M> {
+        self.query = self.query.ancestor(key);
+        self
+    }
+
+    /// Start yielding results from a cursor (see [`Query::start_cursor`]).
+    pub fn start_cursor(mut self, cursor: Cursor) -> ModelQuery<M> {
+        self.query = self.query.start_cursor(cursor);
+        self
+    }
+
+    /// Stop yielding results at a cursor (see [`Query::end_cursor`]).
+    pub fn end_cursor(mut self, cursor: Cursor) -> ModelQuery<M> {
+        self.query = self.query.end_cursor(cursor);
+        self
+    }
+
+    /// Set the read options of the query (see [`Query::read_options`]).
+    pub fn read_options(mut self, read_options: ReadOptions) -> ModelQuery<M> {
+        self.query = self.query.read_options(read_options);
+        self
+    }
+
+    /// Get the underlying untyped query.
+    pub fn into_query(self) -> Query {
+        self.query
+    }
+
+    /// Runs the query and returns the resulting models.
+    pub async fn fetch(self) -> Result<Vec<M>, Error>
+    where
+        M: FromEntity + Send,
+    {
+        self.stream().try_collect().await
+    }
+
+    /// Runs the query and lazily streams the resulting models.
+    pub fn stream(self) -> impl Stream<Item = Result<M, Error>> + Send + Unpin
+    where
+        M: FromEntity + Send,
+    {
+        self.client
+            .query_stream(self.query)
+            .and_then(|entity| future::ready(M::from_entity(entity).map_err(Error::from)))
+    }
+
+    /// Runs the query and only returns the keys of the matching entities.
+    pub async fn keys(mut self) -> Result<Vec<Key>, Error> {
+        self.client.query_keys(self.query).await
+    }
+
+    /// Counts the entities matching the query, without fetching them.
+    pub async fn count(mut self) -> Result<i64, Error> {
+        self.client.count(self.query).await
+    }
+}
@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::datastore::key::KEY_NEWTYPE_NAME;
+use crate::datastore::Value;
+use crate::error::ConvertError;
+
+/// Converts any type implementing `serde::Serialize` to a Datastore value.
+///
+/// Structs and maps are converted to entity values, sequences and tuples to array values,
+/// bytes to blob values and `None` or `()` to null values.
+/// Unit enum variants are converted to their names, and other variants to an entity value
+/// holding the variant's contents under the variant's name.
+/// Keys are converted to key values.
+///
+/// ```
+/// # use google_cloud::datastore::{self, Value};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     #[serde(rename = "fullName")]
+///     name: String,
+///     tags: Vec<String>,
+///     #[serde(skip)]
+///     session: Option<String>,
+/// }
+///
+/// let user = User {
+///     name: String::from("John Doe"),
+///     tags: vec![String::from("admin")],
+///     session: None,
+/// };
+/// let value = datastore::to_value(&user).unwrap();
+/// match value {
+///     Value::EntityValue(properties) => {
+///         assert!(properties.contains_key("fullName"));
+///         assert!(!properties.contains_key("session"));
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn to_value<T>(value: &T) -> Result<Value, ConvertError>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer)
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArrayVariant;
+    type SerializeMap = SerializeEntity;
+    type SerializeStruct = SerializeEntity;
+    type SerializeStructVariant = SerializeEntityVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ConvertError> {
+        Ok(Value::BooleanValue(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ConvertError> {
+        Ok(Value::IntegerValue(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ConvertError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ConvertError> {
+        let v = i64::try_from(v).map_err(|_| {
+            ConvertError::Custom(format!(
+                "integer `{}` does not fit in a 64-bit signed integer",
+                v
+            ))
+        })?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ConvertError> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ConvertError> {
+        Ok(Value::DoubleValue(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ConvertError> {
+        Ok(Value::StringValue(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ConvertError> {
+        Ok(Value::StringValue(String::from(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ConvertError> {
+        Ok(Value::BlobValue(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, ConvertError> {
+        Ok(Value::NullValue)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ConvertError> {
+        Ok(Value::NullValue)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, ConvertError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, ConvertError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        match value.serialize(self)? {
+            Value::StringValue(key) if name == KEY_NEWTYPE_NAME => {
+                Ok(Value::KeyValue(key.parse()?))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut properties = HashMap::with_capacity(1);
+        properties.insert(String::from(variant), to_value(value)?);
+        Ok(Value::EntityValue(properties))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, ConvertError> {
+        Ok(SerializeArray {
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArrayVariant, ConvertError> {
+        Ok(SerializeArrayVariant {
+            variant,
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeEntity, ConvertError> {
+        Ok(SerializeEntity {
+            properties: HashMap::with_capacity(len.unwrap_or_default()),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SerializeEntity, ConvertError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeEntityVariant, ConvertError> {
+        Ok(SerializeEntityVariant {
+            variant,
+            properties: HashMap::with_capacity(len),
+        })
+    }
+}
+
+struct SerializeArray {
+    values: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.values.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::ArrayValue(self.values))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeArrayVariant {
+    variant: &'static str,
+    values: Vec<Value>,
+}
+
+impl ser::SerializeTupleVariant for SerializeArrayVariant {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.values.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        let mut properties = HashMap::with_capacity(1);
+        properties.insert(String::from(self.variant), Value::ArrayValue(self.values));
+        Ok(Value::EntityValue(properties))
+    }
+}
+
+struct SerializeEntity {
+    properties: HashMap<String, Value>,
+    next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeEntity {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.next_key = Some(key.serialize(PropertyNameSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .next_key
+            .take()
+            .expect("`serialize_value` called before `serialize_key`");
+        self.properties.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::EntityValue(self.properties))
+    }
+}
+
+impl ser::SerializeStruct for SerializeEntity {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.properties.insert(String::from(key), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::EntityValue(self.properties))
+    }
+}
+
+struct SerializeEntityVariant {
+    variant: &'static str,
+    properties: HashMap<String, Value>,
+}
+
+impl ser::SerializeStructVariant for SerializeEntityVariant {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.properties.insert(String::from(key), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        let mut properties = HashMap::with_capacity(1);
+        properties.insert(
+            String::from(self.variant),
+            Value::EntityValue(self.properties),
+        );
+        Ok(Value::EntityValue(properties))
+    }
+}
+
+/// Serializes map keys into property names, only accepting strings and integers.
+struct PropertyNameSerializer;
+
+impl PropertyNameSerializer {
+    fn unsupported(kind: &str) -> ConvertError {
+        ConvertError::UnexpectedPropertyType {
+            expected: String::from("string"),
+            got: String::from(kind),
+        }
+    }
+}
+
+impl ser::Serializer for PropertyNameSerializer {
+    type Ok = String;
+    type Error = ConvertError;
+
+    type SerializeSeq = Impossible<String, ConvertError>;
+    type SerializeTuple = Impossible<String, ConvertError>;
+    type SerializeTupleStruct = Impossible<String, ConvertError>;
+    type SerializeTupleVariant = Impossible<String, ConvertError>;
+    type SerializeMap = Impossible<String, ConvertError>;
+    type SerializeStruct = Impossible<String, ConvertError>;
+    type SerializeStructVariant = Impossible<String, ConvertError>;
+
+    fn serialize_bool(self, _: bool) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("bool"))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("double"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("double"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, ConvertError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, ConvertError> {
+        Ok(String::from(v))
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("blob"))
+    }
+
+    fn serialize_none(self) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("null"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<String, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("null"))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("null"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, ConvertError> {
+        Ok(String::from(variant))
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<String, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, ConvertError>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(PropertyNameSerializer::unsupported("entity"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("array"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("array"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("array"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("entity"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("entity"))
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("entity"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, ConvertError> {
+        Err(PropertyNameSerializer::unsupported("entity"))
+    }
+}
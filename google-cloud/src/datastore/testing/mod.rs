@@ -0,0 +1,100 @@
+//! It implements the Datastore gRPC service over a local port, so the regular [`Client`] is used
+//! against it, without credentials and without any access to Google Cloud.
+//!
+//! The fake supports lookups, queries (filters, ordering, ancestors, projections, offsets, limits
+//! and cursors), aggregations, commits with optimistic transactions, and ID allocations.
+//! GQL queries are not supported.
+//!
+//! ```
+//! # use google_cloud::datastore::{Error, Filter, IntoValue, Key, Query};
+//! use google_cloud::datastore::testing::FakeDatastore;
+//! use std::collections::HashMap;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Error> {
+//! let fake = FakeDatastore::start().await?;
+//! let mut client = fake.client("my-project").await?;
+//!
+//! let mut properties = HashMap::new();
+//! properties.insert(String::from("name"), "john".into_value());
+//! properties.insert(String::from("age"), 32.into_value());
+//! let key = client.put((Key::new("users"), properties)).await?.unwrap();
+//!
+//! let query = Query::new("users").filter(Filter::GreaterThan("age".into(), 18.into_value()));
+//! let users = client.query(query).await?;
+//! assert_eq!(users.len(), 1);
+//! assert_eq!(users[0].key(), &key);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Client`]: crate::datastore::Client
+
+mod query;
+mod service;
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use futures::stream;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tonic::transport::Server;
+
+use crate::datastore::api::datastore_server::DatastoreServer;
+use crate::datastore::testing::service::Service;
+use crate::datastore::{Client, Error};
+
+/// An in-memory fake of Datastore, served on a local port.
+///
+/// Every fake starts empty and keeps its own data, so tests using distinct fakes are isolated
+/// from each other. The server is stopped when the fake is dropped.
+pub struct FakeDatastore {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeDatastore {
+    /// Starts a new fake, listening on a random local port.
+    pub async fn start() -> Result<FakeDatastore, Error> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = listener.local_addr()?;
+        let incoming = stream::unfold(listener, |listener| async move {
+            let connection = listener.accept().await.map(|(stream, _)| stream);
+            Some((connection, listener))
+        });
+
+        let (shutdown, signal) = oneshot::channel();
+        let server = Server::builder()
+            .add_service(DatastoreServer::new(Service::default()))
+            .serve_with_incoming_shutdown(incoming, async {
+                let _ = signal.await;
+            });
+        tokio::spawn(server);
+
+        Ok(FakeDatastore {
+            address,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Get the local address the fake listens on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Creates a client connected to the fake, for the specified project.
+    ///
+    /// The fake can also be reached by [`Client::new`], by setting the `DATASTORE_EMULATOR_HOST`
+    /// environment variable to its [address](FakeDatastore::address).
+    pub async fn client(&self, project_name: impl Into<String>) -> Result<Client, Error> {
+        Client::from_emulator(project_name, self.address.to_string()).await
+    }
+}
+
+impl Drop for FakeDatastore {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use tonic::Status;
+
+use crate::datastore::api;
+use crate::datastore::api::property_filter::Operator;
+use crate::datastore::{Client, Entity, Key, KeyID, Value};
+
+/// A parsed query filter.
+enum Condition {
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Ancestor(Key),
+    Property {
+        name: String,
+        op: Operator,
+        value: Value,
+    },
+}
+
+/// Collects the entities of a namespace matching a query, in the order requested by the query.
+///
+/// Offsets, limits and cursors are not applied here, since they depend on the position of the results.
+pub(crate) fn matching<'a, I>(
+    entities: I,
+    namespace: Option<&str>,
+    query: &api::Query,
+) -> Result<Vec<&'a Entity>, Status>
+where
+    I: IntoIterator<Item = &'a Entity>,
+{
+    let kind = match query.kind.as_slice() {
+        [] => None,
+        [kind] => Some(kind.name.as_str()),
+        _ => {
+            return Err(Status::invalid_argument(
+                "only a single kind can be queried at once",
+            ))
+        }
+    };
+    let condition = query.filter.as_ref().map(parse_filter).transpose()?;
+    let order = query
+        .order
+        .iter()
+        .map(|order| {
+            let name = property_name(order.property.as_ref())?;
+            let descending = order.direction == api::property_order::Direction::Descending as i32;
+            Ok((name, descending))
+        })
+        .collect::<Result<Vec<_>, Status>>()?;
+    let projection = query
+        .projection
+        .iter()
+        .map(|projection| property_name(projection.property.as_ref()))
+        .collect::<Result<Vec<_>, Status>>()?;
+    let distinct_on = query
+        .distinct_on
+        .iter()
+        .map(|property| property_name(Some(property)))
+        .collect::<Result<Vec<_>, Status>>()?;
+
+    //? Entities without indexed values for the ordered or projected properties are never returned.
+    let mut results: Vec<(&Entity, Vec<Value>)> = entities
+        .into_iter()
+        .filter(|entity| entity.key().get_namespace() == namespace)
+        .filter(|entity| kind.is_none_or(|kind| entity.key().get_kind() == kind))
+        .filter(|entity| {
+            condition
+                .as_ref()
+                .is_none_or(|condition| matches(entity, condition))
+        })
+        .filter(|entity| {
+            projection
+                .iter()
+                .all(|name| !indexed_values(entity, name).is_empty())
+        })
+        .filter_map(|entity| {
+            let sort_values = order
+                .iter()
+                .map(|(name, descending)| {
+                    let values = indexed_values(entity, name).into_iter();
+                    let value = if *descending {
+                        values.max_by(|a, b| compare(a, b))
+                    } else {
+                        values.min_by(|a, b| compare(a, b))
+                    };
+                    value.map(Cow::into_owned)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some((entity, sort_values))
+        })
+        .collect();
+
+    results.sort_by(|(entity_a, values_a), (entity_b, values_b)| {
+        let ordering = values_a
+            .iter()
+            .zip(values_b.iter())
+            .zip(order.iter())
+            .map(|((a, b), (_, descending))| {
+                let ordering = compare(a, b);
+                if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| *ordering != Ordering::Equal);
+        ordering.unwrap_or_else(|| compare_keys(entity_a.key(), entity_b.key()))
+    });
+
+    let mut results: Vec<&Entity> = results.into_iter().map(|(entity, _)| entity).collect();
+    if !distinct_on.is_empty() {
+        let mut seen: Vec<Vec<Option<Value>>> = Vec::new();
+        results.retain(|entity| {
+            let values: Vec<Option<Value>> = distinct_on
+                .iter()
+                .map(|name| first_indexed_value(entity, name))
+                .collect();
+            let duplicate = seen.iter().any(|other| {
+                other.iter().zip(values.iter()).all(|(a, b)| match (a, b) {
+                    (Some(a), Some(b)) => equal(a, b),
+                    (None, None) => true,
+                    _ => false,
+                })
+            });
+            if !duplicate {
+                seen.push(values);
+            }
+            !duplicate
+        });
+    }
+
+    Ok(results)
+}
+
+/// Get the values of a property of an entity, as they would appear in Datastore indexes.
+///
+/// Nested properties are reached through their dotted path and arrays are flattened,
+/// while properties excluded from indexes (or nested within excluded ones) have no values.
+pub(crate) fn indexed_values<'a>(entity: &'a Entity, path: &str) -> Vec<Cow<'a, Value>> {
+    if path == Client::KEY_PROPERTY {
+        return vec![Cow::Owned(Value::KeyValue(entity.key().clone()))];
+    }
+    let excluded = path
+        .match_indices('.')
+        .map(|(index, _)| &path[..index])
+        .chain(Some(path))
+        .any(|prefix| entity.is_excluded_from_indexes(prefix));
+    if excluded {
+        return Vec::new();
+    }
+
+    let mut values = vec![entity.properties()];
+    for name in path.split('.') {
+        values = values
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::EntityValue(properties) => properties.get(name),
+                _ => None,
+            })
+            .flat_map(|value| match value {
+                Value::ArrayValue(values) => values.iter().collect(),
+                value => vec![value],
+            })
+            .collect();
+    }
+
+    values
+        .into_iter()
+        .filter(|value| !matches!(value, Value::EntityValue(_)))
+        .map(Cow::Borrowed)
+        .collect()
+}
+
+/// Get the first indexed value of a property of an entity, if any.
+pub(crate) fn first_indexed_value(entity: &Entity, path: &str) -> Option<Value> {
+    indexed_values(entity, path)
+        .into_iter()
+        .next()
+        .map(Cow::into_owned)
+}
+
+fn property_name(property: Option<&api::PropertyReference>) -> Result<String, Status> {
+    match property {
+        Some(property) if !property.name.is_empty() => Ok(property.name.clone()),
+        _ => Err(Status::invalid_argument("missing property name")),
+    }
+}
+
+fn parse_filter(filter: &api::Filter) -> Result<Condition, Status> {
+    use api::filter::FilterType;
+
+    match filter.filter_type.as_ref() {
+        Some(FilterType::CompositeFilter(filter)) => {
+            let filters = filter
+                .filters
+                .iter()
+                .map(parse_filter)
+                .collect::<Result<_, _>>()?;
+            match api::composite_filter::Operator::from_i32(filter.op) {
+                Some(api::composite_filter::Operator::And) => Ok(Condition::And(filters)),
+                Some(api::composite_filter::Operator::Or) => Ok(Condition::Or(filters)),
+                _ => Err(Status::invalid_argument(
+                    "unknown composite filter operator",
+                )),
+            }
+        }
+        Some(FilterType::PropertyFilter(filter)) => {
+            let name = property_name(filter.property.as_ref())?;
+            let value = filter
+                .value
+                .clone()
+                .and_then(|value| value.value_type)
+                .map(Value::from)
+                .ok_or_else(|| Status::invalid_argument("missing filter value"))?;
+            match Operator::from_i32(filter.op) {
+                Some(Operator::HasAncestor) => match value {
+                    Value::KeyValue(key) if name == Client::KEY_PROPERTY => {
+                        Ok(Condition::Ancestor(key))
+                    }
+                    _ => Err(Status::invalid_argument(
+                        "ancestor filters must compare `__key__` to a key",
+                    )),
+                },
+                Some(Operator::In) | Some(Operator::NotIn)
+                    if !matches!(value, Value::ArrayValue(_)) =>
+                {
+                    Err(Status::invalid_argument(
+                        "`IN` and `NOT_IN` filters must compare to an array",
+                    ))
+                }
+                Some(Operator::Unspecified) | None => {
+                    Err(Status::invalid_argument("unknown property filter operator"))
+                }
+                Some(op) => Ok(Condition::Property { name, op, value }),
+            }
+        }
+        None => Err(Status::invalid_argument("missing filter")),
+    }
+}
+
+fn matches(entity: &Entity, condition: &Condition) -> bool {
+    match condition {
+        Condition::And(conditions) => conditions
+            .iter()
+            .all(|condition| matches(entity, condition)),
+        Condition::Or(conditions) => conditions
+            .iter()
+            .any(|condition| matches(entity, condition)),
+        Condition::Ancestor(ancestor) => {
+            let mut key = Some(entity.key());
+            while let Some(current) = key {
+                if current == ancestor {
+                    return true;
+                }
+                key = current.get_parent();
+            }
+            false
+        }
+        //? Multi-valued properties match as soon as any of their values does.
+        Condition::Property { name, op, value } => indexed_values(entity, name)
+            .iter()
+            .any(|candidate| satisfies(candidate, *op, value)),
+    }
+}
+
+fn satisfies(candidate: &Value, op: Operator, value: &Value) -> bool {
+    //? Range comparisons never match values of a different type.
+    let same_type = candidate.type_name() == value.type_name();
+    match (op, value) {
+        (Operator::Equal, value) => equal(candidate, value),
+        (Operator::NotEqual, value) => !equal(candidate, value),
+        (Operator::LessThan, value) => same_type && compare(candidate, value) == Ordering::Less,
+        (Operator::LessThanOrEqual, value) => {
+            same_type && compare(candidate, value) != Ordering::Greater
+        }
+        (Operator::GreaterThan, value) => {
+            same_type && compare(candidate, value) == Ordering::Greater
+        }
+        (Operator::GreaterThanOrEqual, value) => {
+            same_type && compare(candidate, value) != Ordering::Less
+        }
+        (Operator::In, Value::ArrayValue(values)) => {
+            values.iter().any(|value| equal(candidate, value))
+        }
+        (Operator::NotIn, Value::ArrayValue(values)) => {
+            !values.iter().any(|value| equal(candidate, value))
+        }
+        _ => false,
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    a.type_name() == b.type_name() && compare(a, b) == Ordering::Equal
+}
+
+/// Get the position of a value's type in the Datastore ordering of values.
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::NullValue => 0,
+        Value::IntegerValue(_) | Value::TimestampValue(_) => 1,
+        Value::BooleanValue(_) => 2,
+        Value::BlobValue(_) => 3,
+        Value::StringValue(_) => 4,
+        Value::DoubleValue(_) => 5,
+        Value::GeoPointValue(_, _) => 6,
+        Value::KeyValue(_) => 7,
+        Value::EntityValue(_) | Value::ArrayValue(_) => 8,
+    }
+}
+
+/// Compares two values the way Datastore orders them in its indexes.
+pub(crate) fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::IntegerValue(a), Value::IntegerValue(b)) => a.cmp(b),
+        (Value::TimestampValue(a), Value::TimestampValue(b)) => a.cmp(b),
+        (Value::IntegerValue(a), Value::TimestampValue(b)) => a.cmp(&b.timestamp_micros()),
+        (Value::TimestampValue(a), Value::IntegerValue(b)) => a.timestamp_micros().cmp(b),
+        (Value::BooleanValue(a), Value::BooleanValue(b)) => a.cmp(b),
+        (Value::BlobValue(a), Value::BlobValue(b)) => a.cmp(b),
+        (Value::StringValue(a), Value::StringValue(b)) => a.cmp(b),
+        (Value::DoubleValue(a), Value::DoubleValue(b)) => a.total_cmp(b),
+        (Value::GeoPointValue(lat_a, lng_a), Value::GeoPointValue(lat_b, lng_b)) => {
+            lat_a.total_cmp(lat_b).then_with(|| lng_a.total_cmp(lng_b))
+        }
+        (Value::KeyValue(a), Value::KeyValue(b)) => compare_keys(a, b),
+        (a, b) => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Compares two keys, path element by path element starting from their root.
+pub(crate) fn compare_keys(a: &Key, b: &Key) -> Ordering {
+    fn path(key: &Key) -> Vec<&Key> {
+        let mut path = Vec::new();
+        let mut current = Some(key);
+        while let Some(key) = current {
+            path.push(key);
+            current = key.get_parent();
+        }
+        path.reverse();
+        path
+    }
+    fn compare_ids(a: &KeyID, b: &KeyID) -> Ordering {
+        match (a, b) {
+            (KeyID::IntID(a), KeyID::IntID(b)) => a.cmp(b),
+            (KeyID::StringID(a), KeyID::StringID(b)) => a.cmp(b),
+            (KeyID::Incomplete, KeyID::Incomplete) => Ordering::Equal,
+            (KeyID::Incomplete, _) | (KeyID::IntID(_), KeyID::StringID(_)) => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    }
+
+    let ordering = a.get_namespace().cmp(&b.get_namespace());
+    let (path_a, path_b) = (path(a), path(b));
+    let elements = path_a.iter().zip(path_b.iter()).map(|(a, b)| {
+        a.get_kind()
+            .cmp(b.get_kind())
+            .then_with(|| compare_ids(a.get_id(), b.get_id()))
+    });
+    elements
+        .fold(ordering, Ordering::then)
+        .then_with(|| path_a.len().cmp(&path_b.len()))
+}
@@ -0,0 +1,744 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tonic::{Request, Response, Status};
+
+use crate::datastore::api;
+use crate::datastore::api::datastore_server::Datastore;
+use crate::datastore::api::query_result_batch::MoreResultsType;
+use crate::datastore::client::{convert_entity, convert_key, convert_value};
+use crate::datastore::testing::query;
+use crate::datastore::{Client, Entity, Key, KeyID, Mutation, Value};
+
+/// The maximum number of results returned in a single query batch.
+const BATCH_SIZE: usize = 300;
+
+/// The Datastore service implemented by the fake, sharing its state across connections.
+#[derive(Default)]
+pub(crate) struct Service {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    projects: HashMap<String, Project>,
+    transactions: HashMap<Vec<u8>, TransactionState>,
+    next_transaction: u64,
+    version: i64,
+}
+
+#[derive(Default)]
+struct Project {
+    entities: HashMap<Key, Entity>,
+    //? Versions are kept for deleted entities too, so that transactions notice deletions.
+    versions: HashMap<Key, i64>,
+    last_id: i64,
+}
+
+struct TransactionState {
+    project_id: String,
+    read_only: bool,
+    reads: HashMap<Key, i64>,
+}
+
+/// The range of ordered query results selected by cursors, an offset and a limit.
+struct Window {
+    /// The position the results start at, after the start cursor and the offset.
+    first: usize,
+    /// The position the results end at, given the end cursor and the limit.
+    last: usize,
+    /// The number of results skipped because of the offset.
+    skipped: usize,
+    /// Whether the end cursor stopped the query before the end of the results.
+    stopped_by_cursor: bool,
+}
+
+impl Project {
+    fn version(&self, key: &Key) -> i64 {
+        self.versions.get(key).copied().unwrap_or_default()
+    }
+
+    /// Completes a key with an ID which is not used by any other entity.
+    fn allocate(&mut self, key: &Key) -> Key {
+        loop {
+            self.last_id += 1;
+            let key = key.clone().id(self.last_id);
+            if !self.versions.contains_key(&key) {
+                break key;
+            }
+        }
+    }
+
+    /// Prevents a key's ID from being allocated.
+    fn reserve(&mut self, key: &Key) {
+        if let KeyID::IntID(id) = key.get_id() {
+            self.last_id = self.last_id.max(*id);
+        }
+    }
+}
+
+impl State {
+    fn project(&mut self, project_id: &str) -> &mut Project {
+        self.projects.entry(String::from(project_id)).or_default()
+    }
+
+    /// Get the transaction a read is made in, if any.
+    fn read_transaction(
+        &self,
+        read_options: Option<&api::ReadOptions>,
+    ) -> Result<Option<Vec<u8>>, Status> {
+        use api::read_options::ConsistencyType;
+
+        match read_options.and_then(|options| options.consistency_type.as_ref()) {
+            Some(ConsistencyType::Transaction(id)) if self.transactions.contains_key(id) => {
+                Ok(Some(id.clone()))
+            }
+            Some(ConsistencyType::Transaction(_)) => {
+                Err(Status::invalid_argument("unknown transaction"))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Records the versions of the entities read within a transaction, to detect conflicts on commit.
+    fn record_reads<'a, I>(&mut self, transaction: Option<&Vec<u8>>, project_id: &str, keys: I)
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        let transactions = &mut self.transactions;
+        let transaction = match transaction.and_then(|id| transactions.get_mut(id)) {
+            Some(transaction) => transaction,
+            None => return,
+        };
+        let project = self.projects.entry(String::from(project_id)).or_default();
+        for key in keys {
+            let version = project.version(key);
+            transaction.reads.entry(key.clone()).or_insert(version);
+        }
+    }
+
+    fn lookup(&mut self, request: api::LookupRequest) -> Result<api::LookupResponse, Status> {
+        let project_id = request.project_id;
+        let keys = request
+            .keys
+            .into_iter()
+            .map(decode_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.iter().any(Key::is_incomplete) {
+            return Err(Status::invalid_argument("cannot look up incomplete keys"));
+        }
+        let transaction = self.read_transaction(request.read_options.as_ref())?;
+        self.record_reads(transaction.as_ref(), &project_id, &keys);
+
+        let project = self.project(&project_id);
+        let mut response = api::LookupResponse::default();
+        for key in keys {
+            let version = project.version(&key);
+            match project.entities.get(&key) {
+                Some(entity) => response.found.push(api::EntityResult {
+                    entity: Some(convert_entity(&project_id, entity.clone())),
+                    version,
+                    cursor: Vec::new(),
+                }),
+                None => response.missing.push(api::EntityResult {
+                    entity: Some(api::Entity {
+                        key: Some(convert_key(&project_id, &key)),
+                        properties: HashMap::new(),
+                    }),
+                    version,
+                    cursor: Vec::new(),
+                }),
+            }
+        }
+
+        Ok(response)
+    }
+
+    fn run_query(
+        &mut self,
+        request: api::RunQueryRequest,
+    ) -> Result<api::RunQueryResponse, Status> {
+        use api::entity_result::ResultType;
+        use api::run_query_request::QueryType;
+
+        let project_id = request.project_id;
+        let query = match request.query_type {
+            Some(QueryType::Query(query)) => query,
+            Some(QueryType::GqlQuery(_)) => {
+                return Err(Status::unimplemented("GQL queries are not supported"))
+            }
+            None => return Err(Status::invalid_argument("missing query")),
+        };
+        let namespace = partition_namespace(request.partition_id);
+        let transaction = self.read_transaction(request.read_options.as_ref())?;
+        let snapshot_version = self.version;
+
+        let project = self.project(&project_id);
+        let results = query::matching(project.entities.values(), namespace.as_deref(), &query)?;
+        let window = window(results.len(), &query)?;
+        let last = window.last.min(window.first + BATCH_SIZE);
+        let more_results = if last < window.last {
+            MoreResultsType::NotFinished
+        } else if window.stopped_by_cursor {
+            MoreResultsType::MoreResultsAfterCursor
+        } else if last < results.len() {
+            MoreResultsType::MoreResultsAfterLimit
+        } else {
+            MoreResultsType::NoMoreResults
+        };
+
+        let projection: Vec<&str> = query
+            .projection
+            .iter()
+            .filter_map(|projection| projection.property.as_ref())
+            .map(|property| property.name.as_str())
+            .collect();
+        let result_type = match projection.as_slice() {
+            [] => ResultType::Full,
+            [Client::KEY_PROPERTY] => ResultType::KeyOnly,
+            _ => ResultType::Projection,
+        };
+
+        let entities: Vec<Entity> = results[window.first..last]
+            .iter()
+            .map(|entity| match result_type {
+                ResultType::Full => (*entity).clone(),
+                _ => project_entity(entity, &projection),
+            })
+            .collect();
+        let entity_results = entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| api::EntityResult {
+                entity: Some(convert_entity(&project_id, entity.clone())),
+                version: match result_type {
+                    ResultType::Full => project.version(entity.key()),
+                    _ => 0,
+                },
+                cursor: encode_cursor(window.first + index + 1),
+            })
+            .collect();
+        let batch = api::QueryResultBatch {
+            skipped_results: window.skipped as i32,
+            skipped_cursor: if window.skipped > 0 {
+                encode_cursor(window.first)
+            } else {
+                Vec::new()
+            },
+            entity_result_type: result_type as i32,
+            entity_results,
+            end_cursor: encode_cursor(last),
+            more_results: more_results as i32,
+            snapshot_version,
+        };
+        self.record_reads(
+            transaction.as_ref(),
+            &project_id,
+            entities.iter().map(Entity::key),
+        );
+
+        Ok(api::RunQueryResponse {
+            batch: Some(batch),
+            query: None,
+        })
+    }
+
+    fn run_aggregation_query(
+        &mut self,
+        request: api::RunAggregationQueryRequest,
+    ) -> Result<api::RunAggregationQueryResponse, Status> {
+        use api::aggregation_query::aggregation::Operator;
+        use api::run_aggregation_query_request::QueryType;
+
+        let project_id = request.project_id;
+        let aggregation_query = match request.query_type {
+            Some(QueryType::AggregationQuery(query)) => query,
+            Some(QueryType::GqlQuery(_)) => {
+                return Err(Status::unimplemented("GQL queries are not supported"))
+            }
+            None => return Err(Status::invalid_argument("missing query")),
+        };
+        let query = match aggregation_query.query_type {
+            Some(api::aggregation_query::QueryType::NestedQuery(query)) => query,
+            None => return Err(Status::invalid_argument("missing nested query")),
+        };
+        let namespace = partition_namespace(request.partition_id);
+
+        let project = self.project(&project_id);
+        let results = query::matching(project.entities.values(), namespace.as_deref(), &query)?;
+        let window = window(results.len(), &query)?;
+        let results = &results[window.first..window.last];
+
+        let mut aggregate_properties = HashMap::new();
+        for aggregation in aggregation_query.aggregations {
+            let value = match aggregation.operator {
+                Some(Operator::Count(count)) => {
+                    let up_to = count.up_to.map_or(i64::MAX, |up_to| up_to.max(0));
+                    Value::IntegerValue((results.len() as i64).min(up_to))
+                }
+                Some(Operator::Sum(sum)) => {
+                    let name = sum.property.map(|property| property.name);
+                    let values = numeric_values(results, name.as_deref());
+                    match values.iter().try_fold(0i64, |total, value| match value {
+                        Value::IntegerValue(value) => total.checked_add(*value),
+                        _ => None,
+                    }) {
+                        Some(total) => Value::IntegerValue(total),
+                        None => Value::DoubleValue(values.iter().map(as_double).sum()),
+                    }
+                }
+                Some(Operator::Avg(avg)) => {
+                    let name = avg.property.map(|property| property.name);
+                    let values = numeric_values(results, name.as_deref());
+                    if values.is_empty() {
+                        Value::NullValue
+                    } else {
+                        let total: f64 = values.iter().map(as_double).sum();
+                        Value::DoubleValue(total / values.len() as f64)
+                    }
+                }
+                None => return Err(Status::invalid_argument("missing aggregation operator")),
+            };
+            aggregate_properties.insert(aggregation.alias, convert_value(&project_id, value));
+        }
+
+        Ok(api::RunAggregationQueryResponse {
+            batch: Some(api::AggregationResultBatch {
+                aggregation_results: vec![api::AggregationResult {
+                    aggregate_properties,
+                }],
+                more_results: MoreResultsType::NoMoreResults as i32,
+            }),
+            query: None,
+        })
+    }
+
+    fn begin_transaction(
+        &mut self,
+        request: api::BeginTransactionRequest,
+    ) -> Result<api::BeginTransactionResponse, Status> {
+        use api::transaction_options::Mode;
+
+        let read_only = matches!(
+            request.transaction_options.and_then(|options| options.mode),
+            Some(Mode::ReadOnly(_))
+        );
+        self.next_transaction += 1;
+        let id = self.next_transaction.to_be_bytes().to_vec();
+        self.transactions.insert(
+            id.clone(),
+            TransactionState {
+                project_id: request.project_id,
+                read_only,
+                reads: HashMap::new(),
+            },
+        );
+
+        Ok(api::BeginTransactionResponse { transaction: id })
+    }
+
+    fn commit(&mut self, request: api::CommitRequest) -> Result<api::CommitResponse, Status> {
+        use api::commit_request::{Mode, TransactionSelector};
+
+        let project_id = request.project_id;
+        let transaction = match (Mode::from_i32(request.mode), request.transaction_selector) {
+            (Some(Mode::Transactional), Some(TransactionSelector::Transaction(id))) => {
+                let transaction = self
+                    .transactions
+                    .remove(&id)
+                    .ok_or_else(|| Status::invalid_argument("unknown transaction"))?;
+                Some(transaction)
+            }
+            (Some(Mode::Transactional), None) => {
+                return Err(Status::invalid_argument(
+                    "transactional commits require a transaction",
+                ))
+            }
+            (_, Some(_)) => {
+                return Err(Status::invalid_argument(
+                    "non-transactional commits cannot have a transaction",
+                ))
+            }
+            _ => None,
+        };
+        let mutations = request
+            .mutations
+            .into_iter()
+            .map(decode_mutation)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let project = self.project(&project_id);
+        if let Some(transaction) = transaction {
+            if transaction.project_id != project_id {
+                return Err(Status::invalid_argument(
+                    "the transaction belongs to another project",
+                ));
+            }
+            if transaction.read_only && !mutations.is_empty() {
+                return Err(Status::invalid_argument(
+                    "read-only transactions cannot write entities",
+                ));
+            }
+            let conflict = transaction
+                .reads
+                .iter()
+                .any(|(key, version)| project.version(key) != *version);
+            if conflict {
+                return Err(Status::aborted(
+                    "too much contention on these datastore entities, please try again",
+                ));
+            }
+        }
+
+        //? Every mutation is validated before applying any, so that failed commits leave no trace.
+        let mut keys = HashSet::new();
+        for mutation in mutations.iter() {
+            let key = mutation.key();
+            if !key.is_incomplete() && !keys.insert(key) {
+                return Err(Status::invalid_argument(
+                    "a commit cannot have more than one mutation on the same entity",
+                ));
+            }
+            let (mutation, base_version) = split_mutation(mutation);
+            if base_version.is_some_and(|version| version != project.version(key)) {
+                continue;
+            }
+            match mutation {
+                Mutation::Insert(_) if project.entities.contains_key(key) => {
+                    return Err(Status::already_exists("entity already exists"));
+                }
+                Mutation::Update(_) if key.is_incomplete() => {
+                    return Err(Status::invalid_argument("cannot update an incomplete key"));
+                }
+                Mutation::Update(_) if !project.entities.contains_key(key) => {
+                    return Err(Status::not_found("no entity to update"));
+                }
+                Mutation::Delete(_) if key.is_incomplete() => {
+                    return Err(Status::invalid_argument("cannot delete an incomplete key"));
+                }
+                _ => {}
+            }
+        }
+
+        let version = self.version + 1;
+        let project = self.projects.entry(project_id.clone()).or_default();
+        let mut mutation_results = Vec::with_capacity(mutations.len());
+        for mutation in mutations {
+            let current_version = project.version(mutation.key());
+            let (write, base_version) = split_owned_mutation(mutation);
+            if base_version.is_some_and(|base_version| base_version != current_version) {
+                mutation_results.push(api::MutationResult {
+                    key: None,
+                    version: current_version,
+                    conflict_detected: true,
+                });
+                continue;
+            }
+            let allocated = match write {
+                Write::Put(mut entity) => {
+                    let allocated = if entity.key.is_incomplete() {
+                        entity.key = project.allocate(&entity.key);
+                        Some(entity.key.clone())
+                    } else {
+                        None
+                    };
+                    entity.version = None;
+                    project.versions.insert(entity.key.clone(), version);
+                    project.entities.insert(entity.key.clone(), entity);
+                    allocated
+                }
+                Write::Delete(key) => {
+                    project.entities.remove(&key);
+                    project.versions.insert(key, version);
+                    None
+                }
+            };
+            mutation_results.push(api::MutationResult {
+                key: allocated.map(|key| convert_key(&project_id, &key)),
+                version,
+                conflict_detected: false,
+            });
+        }
+        self.version = version;
+
+        Ok(api::CommitResponse {
+            mutation_results,
+            index_updates: 0,
+        })
+    }
+
+    fn rollback(&mut self, request: api::RollbackRequest) -> Result<api::RollbackResponse, Status> {
+        match self.transactions.remove(&request.transaction) {
+            Some(_) => Ok(api::RollbackResponse {}),
+            None => Err(Status::invalid_argument("unknown transaction")),
+        }
+    }
+
+    fn allocate_ids(
+        &mut self,
+        request: api::AllocateIdsRequest,
+    ) -> Result<api::AllocateIdsResponse, Status> {
+        let project_id = request.project_id;
+        let keys = request
+            .keys
+            .into_iter()
+            .map(decode_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.iter().any(|key| !key.is_incomplete()) {
+            return Err(Status::invalid_argument(
+                "can only allocate IDs for incomplete keys",
+            ));
+        }
+
+        let project = self.project(&project_id);
+        let keys = keys
+            .iter()
+            .map(|key| convert_key(&project_id, &project.allocate(key)))
+            .collect();
+
+        Ok(api::AllocateIdsResponse { keys })
+    }
+
+    fn reserve_ids(
+        &mut self,
+        request: api::ReserveIdsRequest,
+    ) -> Result<api::ReserveIdsResponse, Status> {
+        let keys = request
+            .keys
+            .into_iter()
+            .map(decode_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.iter().any(Key::is_incomplete) {
+            return Err(Status::invalid_argument("can only reserve complete keys"));
+        }
+
+        let project = self.project(&request.project_id);
+        for key in keys.iter() {
+            project.reserve(key);
+        }
+
+        Ok(api::ReserveIdsResponse {})
+    }
+}
+
+fn partition_namespace(partition_id: Option<api::PartitionId>) -> Option<String> {
+    partition_id
+        .map(|partition_id| partition_id.namespace_id)
+        .filter(|namespace| !namespace.is_empty())
+}
+
+fn decode_key(mut key: api::Key) -> Result<Key, Status> {
+    normalize_key(&mut key)?;
+    Ok(Key::from(key))
+}
+
+fn decode_entity(mut entity: api::Entity) -> Result<Entity, Status> {
+    let key = entity
+        .key
+        .as_mut()
+        .ok_or_else(|| Status::invalid_argument("entities must have a key"))?;
+    normalize_key(key)?;
+    Ok(Entity::from(entity))
+}
+
+fn normalize_key(key: &mut api::Key) -> Result<(), Status> {
+    if key.path.is_empty() {
+        return Err(Status::invalid_argument("keys must have a path"));
+    }
+    key.partition_id.get_or_insert_with(Default::default);
+    Ok(())
+}
+
+fn decode_mutation(mutation: api::Mutation) -> Result<Mutation, Status> {
+    use api::mutation::{ConflictDetectionStrategy, Operation};
+
+    let decoded = match mutation.operation {
+        Some(Operation::Insert(entity)) => Mutation::Insert(decode_entity(entity)?),
+        Some(Operation::Update(entity)) => Mutation::Update(decode_entity(entity)?),
+        Some(Operation::Upsert(entity)) => Mutation::Upsert(decode_entity(entity)?),
+        Some(Operation::Delete(key)) => Mutation::Delete(decode_key(key)?),
+        None => return Err(Status::invalid_argument("missing mutation operation")),
+    };
+    match mutation.conflict_detection_strategy {
+        Some(ConflictDetectionStrategy::BaseVersion(version)) => Ok(decoded.base_version(version)),
+        None => Ok(decoded),
+    }
+}
+
+fn split_mutation(mutation: &Mutation) -> (&Mutation, Option<i64>) {
+    match mutation {
+        Mutation::Conditional {
+            mutation,
+            base_version,
+        } => (mutation.as_ref(), Some(*base_version)),
+        mutation => (mutation, None),
+    }
+}
+
+/// The effect of a mutation on the stored entities, once its condition is checked.
+enum Write {
+    Put(Entity),
+    Delete(Key),
+}
+
+/// Like `split_mutation`, but takes the mutation apart into its effect and its base version.
+fn split_owned_mutation(mutation: Mutation) -> (Write, Option<i64>) {
+    match mutation {
+        Mutation::Insert(entity) | Mutation::Update(entity) | Mutation::Upsert(entity) => {
+            (Write::Put(entity), None)
+        }
+        Mutation::Delete(key) => (Write::Delete(key), None),
+        Mutation::Conditional {
+            mutation,
+            base_version,
+        } => (split_owned_mutation(*mutation).0, Some(base_version)),
+    }
+}
+
+/// Keeps only the projected properties of an entity, under their full path.
+fn project_entity(entity: &Entity, projection: &[&str]) -> Entity {
+    let properties = projection
+        .iter()
+        .filter(|name| **name != Client::KEY_PROPERTY)
+        .filter_map(|name| {
+            let value = query::first_indexed_value(entity, name)?;
+            Some((String::from(*name), value))
+        })
+        .collect();
+    Entity {
+        key: entity.key().clone(),
+        properties: Value::EntityValue(properties),
+        excluded_from_indexes: HashSet::new(),
+        meanings: HashMap::new(),
+        version: None,
+    }
+}
+
+fn numeric_values(entities: &[&Entity], name: Option<&str>) -> Vec<Value> {
+    let name = match name {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+    entities
+        .iter()
+        .flat_map(|entity| query::indexed_values(entity, name))
+        .filter(|value| matches!(**value, Value::IntegerValue(_) | Value::DoubleValue(_)))
+        .map(|value| value.into_owned())
+        .collect()
+}
+
+fn as_double(value: &Value) -> f64 {
+    match value {
+        Value::IntegerValue(value) => *value as f64,
+        Value::DoubleValue(value) => *value,
+        _ => 0.0,
+    }
+}
+
+fn encode_cursor(position: usize) -> Vec<u8> {
+    (position as u64).to_be_bytes().to_vec()
+}
+
+fn decode_cursor(cursor: &[u8]) -> Result<Option<usize>, Status> {
+    if cursor.is_empty() {
+        return Ok(None);
+    }
+    let mut bytes = [0u8; 8];
+    if cursor.len() != bytes.len() {
+        return Err(Status::invalid_argument("invalid cursor"));
+    }
+    bytes.copy_from_slice(cursor);
+    Ok(Some(u64::from_be_bytes(bytes) as usize))
+}
+
+fn window(len: usize, query: &api::Query) -> Result<Window, Status> {
+    let start = decode_cursor(&query.start_cursor)?.unwrap_or(0).min(len);
+    let end = decode_cursor(&query.end_cursor)?
+        .map_or(len, |end| end.min(len))
+        .max(start);
+    let skipped = (query.offset.max(0) as usize).min(end - start);
+    let first = start + skipped;
+    let last = match query.limit {
+        Some(limit) => end.min(first + limit.max(0) as usize),
+        None => end,
+    };
+    Ok(Window {
+        first,
+        last,
+        skipped,
+        stopped_by_cursor: last == end && end < len,
+    })
+}
+
+impl Service {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+#[tonic::async_trait]
+impl Datastore for Service {
+    async fn lookup(
+        &self,
+        request: Request<api::LookupRequest>,
+    ) -> Result<Response<api::LookupResponse>, Status> {
+        let response = self.state().lookup(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn run_query(
+        &self,
+        request: Request<api::RunQueryRequest>,
+    ) -> Result<Response<api::RunQueryResponse>, Status> {
+        let response = self.state().run_query(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn run_aggregation_query(
+        &self,
+        request: Request<api::RunAggregationQueryRequest>,
+    ) -> Result<Response<api::RunAggregationQueryResponse>, Status> {
+        let response = self.state().run_aggregation_query(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn begin_transaction(
+        &self,
+        request: Request<api::BeginTransactionRequest>,
+    ) -> Result<Response<api::BeginTransactionResponse>, Status> {
+        let response = self.state().begin_transaction(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn commit(
+        &self,
+        request: Request<api::CommitRequest>,
+    ) -> Result<Response<api::CommitResponse>, Status> {
+        let response = self.state().commit(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn rollback(
+        &self,
+        request: Request<api::RollbackRequest>,
+    ) -> Result<Response<api::RollbackResponse>, Status> {
+        let response = self.state().rollback(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn allocate_ids(
+        &self,
+        request: Request<api::AllocateIdsRequest>,
+    ) -> Result<Response<api::AllocateIdsResponse>, Status> {
+        let response = self.state().allocate_ids(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+
+    async fn reserve_ids(
+        &self,
+        request: Request<api::ReserveIdsRequest>,
+    ) -> Result<Response<api::ReserveIdsResponse>, Status> {
+        let response = self.state().reserve_ids(request.into_inner())?;
+        Ok(Response::new(response))
+    }
+}
@@ -0,0 +1,162 @@
+use std::borrow::Borrow;
+use std::sync::{Arc, Mutex};
+
+use futures::TryStreamExt;
+
+use crate::datastore::api;
+use crate::datastore::client::{convert_mutation, convert_put};
+use crate::datastore::{
+    Client, Entity, Error, FromEntity, IntoEntity, Key, Mutation, MutationResult, Query,
+    QueryBatch, ReadOptions,
+};
+use crate::error::ConvertError;
+
+/// Represents a Datastore read-write transaction.
+///
+/// Reads are performed against the transaction's consistent snapshot, while writes are buffered
+/// locally until the transaction is committed, at which point they are all applied atomically.
+///
+/// Cloning a transaction gives another handle to the same transaction (sharing its buffered mutations).
+#[derive(Clone)]
+pub struct Transaction {
+    pub(crate) client: Client,
+    pub(crate) id: Vec<u8>,
+    pub(crate) mutations: Arc<Mutex<Vec<api::Mutation>>>,
+}
+
+impl Transaction {
+    pub(crate) fn new(client: Client, id: Vec<u8>) -> Transaction {
+        Transaction {
+            client,
+            id,
+            mutations: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Get the read options for reading from the transaction's consistent snapshot.
+    pub fn read_options(&self) -> ReadOptions {
+        ReadOptions::transaction(self)
+    }
+
+    /// Gets an entity from a key, within the transaction.
+    pub async fn get<T, K>(&mut self, key: K) -> Result<Option<T>, Error>
+    where
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        let results = self.get_all_aligned(Some(key.borrow())).await?;
+        Ok(results.into_iter().next().flatten())
+    }
+
+    /// Gets multiple entities from multiple keys, within the transaction.
+    ///
+    /// Missing entities are skipped, use [`Transaction::get_all_aligned`] to keep track of them.
+    pub async fn get_all<T, K, I>(&mut self, keys: I) -> Result<Vec<T>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        let results = self.get_all_aligned(keys).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Gets multiple entities from multiple keys within the transaction, returning one result per key in the same order.
+    ///
+    /// Missing entities are returned as `None`.
+    pub async fn get_all_aligned<T, K, I>(&mut self, keys: I) -> Result<Vec<Option<T>>, Error>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+        T: FromEntity,
+    {
+        let read_options = self.read_options();
+        self.client.lookup(keys, read_options).await
+    }
+
+    /// Runs a query within the transaction and returns the results.
+    ///
+    /// Only ancestor queries are allowed within transactions.
+    pub async fn query(&mut self, query: Query) -> Result<Vec<Entity>, Error> {
+        let read_options = self.read_options();
+        self.client
+            .run_query(query, read_options)
+            .map_ok(QueryBatch::into_entities)
+            .try_concat()
+            .await
+    }
+
+    /// Buffers the insertion of an entity, to be applied when the transaction commits.
+    pub fn put(&self, entity: impl IntoEntity) -> Result<(), ConvertError> {
+        self.put_all(Some(entity))
+    }
+
+    /// Buffers the insertion of multiple entities, to be applied when the transaction commits.
+    pub fn put_all<T, I>(&self, entities: I) -> Result<(), ConvertError>
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoEntity,
+    {
+        let project_name = self.client.project_name.as_str();
+        let mutations = entities
+            .into_iter()
+            .map(|entity| Ok(convert_put(project_name, entity.into_entity()?)))
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+        self.mutations.lock().unwrap().extend(mutations);
+        Ok(())
+    }
+
+    /// Buffers the deletion of an entity, to be applied when the transaction commits.
+    pub fn delete(&self, key: impl Borrow<Key>) {
+        self.delete_all(Some(key.borrow()))
+    }
+
+    /// Buffers the deletion of multiple entities, to be applied when the transaction commits.
+    pub fn delete_all<T, I>(&self, keys: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Borrow<Key>,
+    {
+        let mutations = keys
+            .into_iter()
+            .map(|key| Mutation::Delete(key.borrow().clone()));
+        self.mutate_all(mutations);
+    }
+
+    /// Buffers a mutation, to be applied when the transaction commits.
+    pub fn mutate(&self, mutation: Mutation) {
+        self.mutate_all(Some(mutation))
+    }
+
+    /// Buffers multiple mutations, to be applied in order when the transaction commits.
+    pub fn mutate_all<I>(&self, mutations: I)
+    where
+        I: IntoIterator<Item = Mutation>,
+    {
+        let project_name = self.client.project_name.as_str();
+        let mutations = mutations
+            .into_iter()
+            .map(|mutation| convert_mutation(project_name, mutation));
+        self.mutations.lock().unwrap().extend(mutations);
+    }
+
+    /// Commits the transaction, atomically applying all of its buffered mutations.
+    ///
+    /// Returns the results of the mutations, in the order they were buffered.
+    /// If an inserted entity's key was incomplete, its result holds the key generated by the store.
+    pub async fn commit(mut self) -> Result<Vec<MutationResult>, Error> {
+        let mutations = std::mem::take(&mut *self.mutations.lock().unwrap());
+        let results = self
+            .client
+            .commit_mutations(mutations, Some(self.id))
+            .await?;
+
+        Ok(results.into_iter().map(MutationResult::from).collect())
+    }
+
+    /// Rolls the transaction back, discarding all of its buffered mutations.
+    pub async fn rollback(mut self) -> Result<(), Error> {
+        self.mutations.lock().unwrap().clear();
+        self.client.rollback(self.id).await
+    }
+}
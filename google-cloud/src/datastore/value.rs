@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::iter::FromIterator;
+use std::ops::Index;
+use std::time::SystemTime;
 
-use chrono::NaiveDateTime;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SubsecRound, Utc};
 
 #[cfg(feature = "bytes")]
 use bytes::Bytes;
@@ -17,6 +19,8 @@ pub use google_cloud_derive::{FromValue, IntoValue};
 /// A value, as stored in Datastore.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
+    /// A null value.
+    NullValue,
     /// A boolean value (true or false).
     BooleanValue(bool),
     /// An integer value.
@@ -24,7 +28,19 @@ pub enum Value {
     /// A floating-point value.
     DoubleValue(f64),
     /// A timestamp value.
-    TimestampValue(chrono::NaiveDateTime),
+    ///
+    /// Datastore stores timestamps with a microsecond precision,
+    /// so the conversions into this value truncate any extra precision.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::{FromValue, IntoValue};
+    /// use chrono::{DateTime, TimeZone, Utc};
+    ///
+    /// let time = Utc.timestamp_opt(1_600_000_000, 123_456_789).unwrap();
+    /// let recovered = DateTime::<Utc>::from_value(time.into_value()).unwrap();
+    /// assert_eq!(recovered.timestamp_subsec_nanos(), 123_456_000);
+    /// ```
+    TimestampValue(DateTime<Utc>),
     /// A key value.
     KeyValue(Key),
     /// A string value.
@@ -43,6 +59,7 @@ impl Value {
     /// Gets the static name of the type of the value.
     pub fn type_name(&self) -> &'static str {
         match self {
+            Value::NullValue => "null",
             Value::BooleanValue(_) => "bool",
             Value::IntegerValue(_) => "integer",
             Value::DoubleValue(_) => "double",
@@ -55,18 +72,256 @@ impl Value {
             Value::ArrayValue(_) => "array",
         }
     }
+
+    /// Is the value null ?
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::NullValue)
+    }
+
+    /// Get the boolean held by the value, if it is a boolean value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::BooleanValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the integer held by the value, if it is an integer value.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::IntoValue;
+    /// assert_eq!(32.into_value().as_i64(), Some(32));
+    /// assert_eq!("32".into_value().as_i64(), None);
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::IntegerValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the floating-point number held by the value, if it is a double value.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::DoubleValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the string held by the value, if it is a string value.
+    ///
+    /// ```
+    /// # use google_cloud::datastore::IntoValue;
+    /// assert_eq!("john".into_value().as_str(), Some("john"));
+    /// assert_eq!(32.into_value().as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::StringValue(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Get the key held by the value, if it is a key value.
+    pub fn as_key(&self) -> Option<&Key> {
+        match self {
+            Value::KeyValue(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Get the properties held by the value, if it is an entity value.
+    ///
+    /// ```
+    /// # use google_cloud::value;
+    /// let value = value! { "name": "john" };
+    /// let properties = value.as_entity().unwrap();
+    /// assert_eq!(properties["name"].as_str(), Some("john"));
+    /// assert_eq!(value["name"].as_entity(), None);
+    /// ```
+    pub fn as_entity(&self) -> Option<&HashMap<String, Value>> {
+        match self {
+            Value::EntityValue(properties) => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable reference to the properties held by the value, if it is an entity value.
+    pub fn as_entity_mut(&mut self) -> Option<&mut HashMap<String, Value>> {
+        match self {
+            Value::EntityValue(properties) => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// Get the values held by the value, if it is an array value.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::ArrayValue(values) => Some(values.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+static NULL: Value = Value::NullValue;
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    /// Get a property of an entity value, or a null value if it is missing or if the value is
+    /// not an entity value.
+    ///
+    /// ```
+    /// # use google_cloud::value;
+    /// let value = value! { "address": { "city": "Paris" } };
+    /// assert_eq!(value["address"]["city"].as_str(), Some("Paris"));
+    /// assert!(value["address"]["zip"].is_null());
+    /// assert!(value["address"]["city"]["name"].is_null());
+    /// ```
+    fn index(&self, name: &str) -> &Value {
+        match self {
+            Value::EntityValue(properties) => properties.get(name).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    /// Get an element of an array value, or a null value if it is out of bounds or if the value
+    /// is not an array value.
+    ///
+    /// ```
+    /// # use google_cloud::value;
+    /// let value = value!(["admin", "staff"]);
+    /// assert_eq!(value[1].as_str(), Some("staff"));
+    /// assert!(value[2].is_null());
+    /// ```
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            Value::ArrayValue(values) => values.get(index).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+/// Constructs a Datastore value from a literal resembling JSON.
+///
+/// Entity values are written as `{ "name": value, ... }` (the braces can be omitted at the top
+/// level), array values as `[value, ...]` and null values as `null`.
+/// Any other value is an expression, converted using [`IntoValue`].
+/// Expressions starting with a brace or a bracket must be wrapped in parentheses.
+///
+/// ```
+/// # use google_cloud::datastore::{IntoValue, Key, Value};
+/// use google_cloud::value;
+///
+/// let age = 32;
+/// let user = value! {
+///     "name": "John Doe",
+///     "age": age + 1,
+///     "manager": Key::new("users").id("jane"),
+///     "address": { "city": "Paris", "zip": null },
+///     "tags": ["admin", { "since": 2020 }],
+/// };
+/// assert_eq!(user["age"], Value::IntegerValue(33));
+/// assert_eq!(user["address"]["city"], "Paris".into_value());
+/// assert!(user["address"]["zip"].is_null());
+/// assert_eq!(user["tags"][1]["since"].as_i64(), Some(2020));
+/// assert_eq!(value!{}, Value::EntityValue(Default::default()));
+/// ```
+///
+/// [`IntoValue`]: crate::datastore::IntoValue
+#[macro_export]
+macro_rules! value {
+    () => {
+        $crate::datastore::Value::EntityValue(::std::collections::HashMap::new())
+    };
+    (null) => {
+        $crate::datastore::Value::NullValue
+    };
+    ([ $($elements:tt)* ]) => {
+        $crate::datastore::Value::ArrayValue($crate::value!(@array [] $($elements)*))
+    };
+    ({ $($properties:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut properties = ::std::collections::HashMap::new();
+        $crate::value!(@entity properties ($($properties)*));
+        $crate::datastore::Value::EntityValue(properties)
+    }};
+    ($name:literal : $($rest:tt)*) => {
+        $crate::value!({ $name : $($rest)* })
+    };
+
+    //? Munches array elements one at a time, accumulating the converted values.
+    (@array [$($values:expr,)*]) => {
+        ::std::vec![$($values,)*]
+    };
+    (@array [$($values:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::value!(@array [$($values,)* $crate::value!(null),] $($($rest)*)?)
+    };
+    (@array [$($values:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::value!(@array [$($values,)* $crate::value!([$($array)*]),] $($($rest)*)?)
+    };
+    (@array [$($values:expr,)*] {$($entity:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::value!(@array [$($values,)* $crate::value!({$($entity)*}),] $($($rest)*)?)
+    };
+    (@array [$($values:expr,)*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::value!(@array [$($values,)* $crate::value!($value),] $($($rest)*)?)
+    };
+
+    //? Munches entity properties one at a time, inserting them into the map.
+    (@entity $properties:ident ()) => {};
+    (@entity $properties:ident ($name:literal : null $(, $($rest:tt)*)?)) => {
+        $properties.insert(::std::string::String::from($name), $crate::value!(null));
+        $crate::value!(@entity $properties ($($($rest)*)?));
+    };
+    (@entity $properties:ident ($name:literal : [$($array:tt)*] $(, $($rest:tt)*)?)) => {
+        $properties.insert(::std::string::String::from($name), $crate::value!([$($array)*]));
+        $crate::value!(@entity $properties ($($($rest)*)?));
+    };
+    (@entity $properties:ident ($name:literal : {$($entity:tt)*} $(, $($rest:tt)*)?)) => {
+        $properties.insert(::std::string::String::from($name), $crate::value!({$($entity)*}));
+        $crate::value!(@entity $properties ($($($rest)*)?));
+    };
+    (@entity $properties:ident ($name:literal : $value:expr $(, $($rest:tt)*)?)) => {
+        $properties.insert(::std::string::String::from($name), $crate::value!($value));
+        $crate::value!(@entity $properties ($($($rest)*)?));
+    };
+
+    ($value:expr) => {
+        $crate::datastore::IntoValue::into_value($value)
+    };
 }
 
 /// Trait for converting a type to a Datastore value.
 pub trait IntoValue {
     /// Converts the type to a Datastore value.
     fn into_value(self) -> Value;
+
+    /// Gets the paths of the properties to exclude from indexes, when stored within an entity.
+    ///
+    /// Nested properties are separated by dots (like `address.city`).
+    fn excluded_from_indexes() -> Vec<String>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
 }
 
 /// Trait for mapping a Datastore value to a type.
 pub trait FromValue: Sized {
     /// Attempts to construct a value of this type from the passed Datastore value.
     fn from_value(value: Value) -> Result<Self, ConvertError>;
+
+    /// Attempts to construct a value of this type for a property missing from an entity.
+    ///
+    /// By default, this fails with `ConvertError::MissingProperty`.
+    fn from_missing(property: &str) -> Result<Self, ConvertError> {
+        Err(ConvertError::MissingProperty(String::from(property)))
+    }
 }
 
 impl IntoValue for Value {
@@ -135,9 +390,28 @@ impl IntoValue for Key {
     }
 }
 
+impl IntoValue for DateTime<Utc> {
+    fn into_value(self) -> Value {
+        Value::TimestampValue(self.trunc_subsecs(6))
+    }
+}
+
+impl IntoValue for DateTime<FixedOffset> {
+    fn into_value(self) -> Value {
+        self.with_timezone(&Utc).into_value()
+    }
+}
+
+impl IntoValue for SystemTime {
+    fn into_value(self) -> Value {
+        DateTime::<Utc>::from(self).into_value()
+    }
+}
+
+/// Naive date-times are interpreted as being in UTC.
 impl IntoValue for NaiveDateTime {
     fn into_value(self) -> Value {
-        Value::TimestampValue(self)
+        self.and_utc().into_value()
     }
 }
 
@@ -155,6 +429,10 @@ where
     fn into_value(self) -> Value {
         Value::ArrayValue(self.into_iter().map(IntoValue::into_value).collect())
     }
+
+    fn excluded_from_indexes() -> Vec<String> {
+        T::excluded_from_indexes()
+    }
 }
 
 impl<T> IntoValue for HashMap<String, T>
@@ -166,6 +444,22 @@ where
     }
 }
 
+impl<T> IntoValue for Option<T>
+where
+    T: IntoValue,
+{
+    fn into_value(self) -> Value {
+        match self {
+            Some(value) => value.into_value(),
+            None => Value::NullValue,
+        }
+    }
+
+    fn excluded_from_indexes() -> Vec<String> {
+        T::excluded_from_indexes()
+    }
+}
+
 impl<T> FromIterator<T> for Value
 where
     T: IntoValue,
@@ -244,8 +538,8 @@ impl FromValue for Key {
     }
 }
 
-impl FromValue for NaiveDateTime {
-    fn from_value(value: Value) -> Result<NaiveDateTime, ConvertError> {
+impl FromValue for DateTime<Utc> {
+    fn from_value(value: Value) -> Result<DateTime<Utc>, ConvertError> {
         match value {
             Value::TimestampValue(value) => Ok(value),
             _ => Err(ConvertError::UnexpectedPropertyType {
@@ -256,6 +550,29 @@ impl FromValue for NaiveDateTime {
     }
 }
 
+/// Timestamps are recovered with a UTC offset.
+impl FromValue for DateTime<FixedOffset> {
+    fn from_value(value: Value) -> Result<DateTime<FixedOffset>, ConvertError> {
+        let value = DateTime::<Utc>::from_value(value)?;
+        Ok(value.fixed_offset())
+    }
+}
+
+impl FromValue for SystemTime {
+    fn from_value(value: Value) -> Result<SystemTime, ConvertError> {
+        let value = DateTime::<Utc>::from_value(value)?;
+        Ok(SystemTime::from(value))
+    }
+}
+
+/// Timestamps are recovered as naive date-times in UTC.
+impl FromValue for NaiveDateTime {
+    fn from_value(value: Value) -> Result<NaiveDateTime, ConvertError> {
+        let value = DateTime::<Utc>::from_value(value)?;
+        Ok(value.naive_utc())
+    }
+}
+
 #[cfg(feature = "bytes")]
 impl FromValue for Bytes {
     fn from_value(value: Value) -> Result<Bytes, ConvertError> {
@@ -290,6 +607,22 @@ where
     }
 }
 
+impl<T> FromValue for Option<T>
+where
+    T: FromValue,
+{
+    fn from_value(value: Value) -> Result<Option<T>, ConvertError> {
+        match value {
+            Value::NullValue => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+
+    fn from_missing(_: &str) -> Result<Option<T>, ConvertError> {
+        Ok(None)
+    }
+}
+
 impl<T> FromValue for HashMap<String, T>
 where
     T: FromValue,
@@ -317,12 +650,13 @@ where
 impl From<ValueType> for Value {
     fn from(value: ValueType) -> Value {
         match value {
-            ValueType::NullValue(_) => unreachable!(),
+            ValueType::NullValue(_) => Value::NullValue,
             ValueType::BooleanValue(val) => Value::BooleanValue(val),
             ValueType::IntegerValue(val) => Value::IntegerValue(val),
             ValueType::DoubleValue(val) => Value::DoubleValue(val),
             ValueType::TimestampValue(val) => {
-                Value::TimestampValue(NaiveDateTime::from_timestamp(val.seconds, val.nanos as u32))
+                let datetime = DateTime::from_timestamp(val.seconds, val.nanos as u32).unwrap();
+                Value::TimestampValue(datetime)
             }
             ValueType::KeyValue(key) => Value::KeyValue(Key::from(key)),
             ValueType::StringValue(val) => Value::StringValue(val),
@@ -21,6 +21,9 @@ pub enum Error {
     /// An environment-related error (missing variable).
     #[error("environment error: {0}")]
     Env(#[from] env::VarError),
+    /// An invalid URI (like a malformed emulator host).
+    #[error("invalid URI: {0}")]
+    InvalidUri(#[from] http::uri::InvalidUri),
     /// Reqwest error (HTTP errors).
     #[cfg(feature = "storage")]
     #[error("HTTP error: {0}")]
@@ -31,6 +34,10 @@ pub enum Error {
     /// authentication-related error.
     #[error("authentication error: {0}")]
     Auth(#[from] AuthError),
+    /// A conditional Datastore write was not applied, because the entity's version did not match.
+    #[cfg(feature = "datastore")]
+    #[error("conflicting concurrent write on entity {0}")]
+    Conflict(crate::datastore::Key),
 }
 
 /// The error type for value conversions.
@@ -47,6 +54,35 @@ pub enum ConvertError {
         /// The name of the actual encountered type.
         got: String,
     },
+    /// A stored enum variant did not match any of the known variants.
+    #[error("unknown enum variant `{0}`")]
+    UnknownVariant(String),
+    /// An encoded value (like a cursor) could not be decoded.
+    #[error("invalid encoding: {0}")]
+    InvalidEncoding(String),
+    /// A custom error, emitted while (de)serializing values using `serde`.
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl serde::ser::Error for ConvertError {
+    fn custom<T: std::fmt::Display>(msg: T) -> ConvertError {
+        ConvertError::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for ConvertError {
+    fn custom<T: std::fmt::Display>(msg: T) -> ConvertError {
+        ConvertError::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> ConvertError {
+        ConvertError::MissingProperty(String::from(field))
+    }
+
+    fn unknown_variant(variant: &str, _expected: &'static [&'static str]) -> ConvertError {
+        ConvertError::UnknownVariant(String::from(variant))
+    }
 }
 
 /// The error type for authentication-related errors.
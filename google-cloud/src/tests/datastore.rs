@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use futures::TryStreamExt;
+
 use crate::datastore;
 use crate::datastore::IntoValue;
 
@@ -31,10 +33,7 @@ async fn datastore_puts_data_successfully() {
     let properties = {
         let mut values = HashMap::new();
         values.insert(String::from("hello"), "world !".into_value());
-        values.insert(
-            String::from("time"),
-            datastore::Value::TimestampValue(chrono::Local::now().naive_local()),
-        );
+        values.insert(String::from("time"), chrono::Utc::now().into_value());
         values
     };
 
@@ -48,3 +47,338 @@ async fn datastore_puts_data_successfully() {
     //? Delete that value from Datastore.
     assert_ok!(client.delete(key).await);
 }
+
+#[tokio::test]
+async fn datastore_runs_transactions_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Prepare Datastore key for a counter.
+    let key = datastore::Key::new("google-cloud-tests")
+        .namespace("test")
+        .id("test-counter");
+
+    //? Increment the counter twice, each time within a transaction.
+    for _ in 0..2 {
+        let key = key.clone();
+        assert_ok!(
+            client
+                .run_in_transaction(|mut tx| {
+                    let key = key.clone();
+                    async move {
+                        let current: Option<HashMap<String, i64>> = tx.get(&key).await?;
+                        let count = current
+                            .and_then(|props| props.get("count").copied())
+                            .unwrap_or(0);
+                        let mut props = HashMap::new();
+                        props.insert(String::from("count"), count + 1);
+                        tx.put((key, props))?;
+                        Ok(())
+                    }
+                })
+                .await
+        );
+    }
+
+    //? Check that both increments were applied.
+    let outcome = assert_ok!(client.get::<HashMap<String, i64>, _>(&key).await);
+    assert_eq!(
+        outcome.and_then(|props| props.get("count").copied()),
+        Some(2)
+    );
+
+    //? Delete that value from Datastore.
+    assert_ok!(client.delete(key).await);
+}
+
+#[tokio::test]
+async fn datastore_paginates_queries_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store a few entities to paginate through.
+    let keys: Vec<_> = (1..=3)
+        .map(|id| {
+            datastore::Key::new("google-cloud-tests-pages")
+                .namespace("test")
+                .id(id)
+        })
+        .collect();
+    let entities = keys.iter().cloned().map(|key| {
+        let mut values = HashMap::new();
+        values.insert(String::from("hello"), "world !".into_value());
+        (key, values)
+    });
+    assert_ok!(client.put_all(entities).await);
+
+    //? Fetch the first page, and resume from its cursor.
+    let query = datastore::Query::new("google-cloud-tests-pages")
+        .namespace("test")
+        .limit(2);
+    let mut batches = client.query_batches(query.clone());
+    let first = assert_ok!(batches.try_next().await).expect("missing first page");
+    assert_eq!(first.entities().len(), 2);
+
+    let cursor: datastore::Cursor = assert_ok!(first.end_cursor().to_string().parse());
+    let second = assert_ok!(client.query(query.start_cursor(cursor)).await);
+    assert_eq!(second.len(), 1);
+
+    //? Delete those values from Datastore.
+    assert_ok!(client.delete_all(keys).await);
+}
+
+#[tokio::test]
+async fn datastore_runs_gql_queries_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store a few entities to query.
+    let keys: Vec<_> = (1..=3)
+        .map(|id| {
+            datastore::Key::new("google-cloud-tests-gql")
+                .namespace("test")
+                .id(id)
+        })
+        .collect();
+    let entities = keys.iter().cloned().zip(1..=3).map(|(key, rank)| {
+        let mut values = HashMap::new();
+        values.insert(String::from("rank"), (rank as i64).into_value());
+        (key, values)
+    });
+    assert_ok!(client.put_all(entities).await);
+
+    //? Query them using both named and positional bindings.
+    let query = datastore::GqlQuery::new(
+        "SELECT * FROM `google-cloud-tests-gql` WHERE rank >= @min AND rank < @1",
+    )
+    .namespace("test")
+    .bind("min", 2)
+    .bind_positional(10);
+    let results: Vec<datastore::Entity> = assert_ok!(client.gql(query).await);
+    assert_eq!(results.len(), 2);
+
+    //? Delete those values from Datastore.
+    assert_ok!(client.delete_all(keys).await);
+}
+
+#[tokio::test]
+async fn datastore_allocates_ids_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Allocate IDs for a parent and a child key, within a namespace.
+    let parent = datastore::Key::new("google-cloud-tests-ids").namespace("test");
+    let parent = assert_ok!(client.allocate_ids(vec![parent]).await).remove(0);
+    assert!(!parent.is_incomplete());
+    assert_eq!(parent.get_namespace(), Some("test"));
+
+    let child = datastore::Key::new("google-cloud-tests-ids")
+        .namespace("test")
+        .parent(parent.clone());
+    let child = assert_ok!(client.allocate_ids(vec![child]).await).remove(0);
+    assert!(!child.is_incomplete());
+    assert_eq!(child.get_parent(), Some(&parent));
+
+    //? Reserve an explicit ID.
+    let reserved = datastore::Key::new("google-cloud-tests-ids")
+        .namespace("test")
+        .id(4242);
+    assert_ok!(client.reserve_ids(vec![reserved]).await);
+}
+
+#[tokio::test]
+async fn datastore_excludes_properties_from_indexes_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store a string too large to be indexed.
+    let key = datastore::Key::new("google-cloud-tests-indexes")
+        .namespace("test")
+        .id(1);
+    let mut values = HashMap::new();
+    values.insert(String::from("text"), "a".repeat(2000).into_value());
+    let entity =
+        assert_ok!(datastore::Entity::new(key.clone(), values)).exclude_from_indexes("text");
+    assert_ok!(client.put(entity).await);
+
+    //? The exclusion is read back along with the entity.
+    let entity: Option<datastore::Entity> = assert_ok!(client.get(&key).await);
+    let entity = entity.expect("missing entity");
+    assert!(entity.is_excluded_from_indexes("text"));
+
+    //? Delete that value from Datastore.
+    assert_ok!(client.delete(key).await);
+}
+
+#[tokio::test]
+async fn datastore_gets_aligned_entities_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store a single entity.
+    let present = datastore::Key::new("google-cloud-tests-aligned")
+        .namespace("test")
+        .id(1);
+    let missing = datastore::Key::new("google-cloud-tests-aligned")
+        .namespace("test")
+        .id(2);
+    let mut values = HashMap::new();
+    values.insert(String::from("hello"), "world !".into_value());
+    assert_ok!(client.put((present.clone(), values)).await);
+
+    //? Results line up with the requested keys.
+    let keys = vec![missing.clone(), present.clone(), missing];
+    let results: Vec<Option<datastore::Entity>> = assert_ok!(client.get_all_aligned(&keys).await);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_none());
+    assert_eq!(
+        results[1].as_ref().map(|entity| entity.key()),
+        Some(&present)
+    );
+    assert!(results[2].is_none());
+
+    //? Delete that value from Datastore.
+    assert_ok!(client.delete(present).await);
+}
+
+#[tokio::test]
+async fn datastore_bulk_writes_successfully() {
+    //? Setup test client.
+    let client = assert_ok!(setup_client().await);
+    let writer = client.bulk_writer().concurrency(4);
+
+    //? Store more entities than a single commit allows.
+    let entities = (1..=1200).map(|id| {
+        let key = datastore::Key::new("google-cloud-tests-bulk")
+            .namespace("test")
+            .id(id);
+        let mut values = HashMap::new();
+        values.insert(String::from("id"), id.into_value());
+        (key, values)
+    });
+    let result = assert_ok!(writer.put_all(entities).await);
+    assert!(result.is_success());
+    let keys: Vec<_> = result.keys().cloned().collect();
+    assert_eq!(keys.len(), 1200);
+
+    //? Delete those values from Datastore.
+    let result = writer.delete_all(keys).await;
+    assert!(result.is_success());
+}
+
+#[tokio::test]
+async fn datastore_detects_conflicting_writes_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store an entity and read its version back.
+    let key = datastore::Key::new("google-cloud-tests-versions")
+        .namespace("test")
+        .id(1);
+    let mut values = HashMap::new();
+    values.insert(String::from("revision"), 1.into_value());
+    assert_ok!(client.put((key.clone(), values.clone())).await);
+    let entity: Option<datastore::Entity> = assert_ok!(client.get(&key).await);
+    let version = entity
+        .and_then(|entity| entity.version())
+        .expect("missing version");
+
+    //? Writing against the current version succeeds, and bumps it.
+    values.insert(String::from("revision"), 2.into_value());
+    let new_version = assert_ok!(
+        client
+            .put_if_version((key.clone(), values.clone()), version)
+            .await
+    );
+    assert!(new_version > version);
+
+    //? Writing against the outdated version fails.
+    let result = client.put_if_version((key.clone(), values), version).await;
+    assert!(matches!(result, Err(crate::error::Error::Conflict(_))));
+
+    //? Delete that value from Datastore.
+    assert_ok!(client.delete_if_version(&key, new_version).await);
+}
+
+#[tokio::test]
+async fn datastore_lists_metadata_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store an entity, to make sure its namespace, kind and properties exist.
+    let key = datastore::Key::new("google-cloud-tests-metadata")
+        .namespace("test")
+        .id(1);
+    let mut values = HashMap::new();
+    values.insert(String::from("count"), 10.into_value());
+    assert_ok!(client.put((key.clone(), values)).await);
+
+    //? List the namespaces and kinds.
+    let namespaces = assert_ok!(client.namespaces().await);
+    assert!(namespaces.iter().any(|namespace| namespace == "test"));
+    let kinds = assert_ok!(client.kinds(Some("test")).await);
+    assert!(kinds
+        .iter()
+        .any(|kind| kind == "google-cloud-tests-metadata"));
+
+    //? List the properties of the kind, with their representations.
+    let properties = assert_ok!(
+        client
+            .properties_in(Some("test"), "google-cloud-tests-metadata")
+            .await
+    );
+    let count = properties
+        .iter()
+        .find(|property| property.name() == "count")
+        .expect("missing property");
+    assert!(count
+        .representations()
+        .contains(&datastore::PropertyRepresentation::Integer));
+
+    //? Delete that value from Datastore.
+    assert_ok!(client.delete(key).await);
+}
+
+#[tokio::test]
+async fn datastore_exports_and_imports_entities_successfully() {
+    //? Setup test client.
+    let mut client = assert_ok!(setup_client().await);
+
+    //? Store an entity to export.
+    let key = datastore::Key::new("google-cloud-tests-io")
+        .namespace("test")
+        .id(1);
+    let mut values = HashMap::new();
+    values.insert(
+        String::from("data"),
+        datastore::Value::BlobValue(vec![0, 1, 2]),
+    );
+    assert_ok!(client.put((key.clone(), values)).await);
+
+    //? Export the kind as newline-delimited JSON.
+    let query = datastore::Query::new("google-cloud-tests-io").namespace("test");
+    let mut exporter = datastore::io::Exporter::new(Vec::new());
+    let count = assert_ok!(exporter.export(client.query_stream(query)).await);
+    assert_eq!(count, 1);
+    let output = exporter.into_inner();
+
+    //? Import it back into another namespace.
+    let importer = datastore::io::Importer::new(client.bulk_writer()).namespace("test-import");
+    let result = importer.import(output.as_slice()).await;
+    assert!(result.is_success());
+
+    let imported_key = datastore::Key::new("google-cloud-tests-io")
+        .namespace("test-import")
+        .id(1);
+    let imported: Option<datastore::Entity> = assert_ok!(client.get(&imported_key).await);
+    let imported = imported.expect("missing imported entity");
+    assert_eq!(
+        imported.properties(),
+        assert_ok!(client.get::<datastore::Entity, _>(&key).await)
+            .unwrap()
+            .properties()
+    );
+
+    //? Delete those values from Datastore.
+    assert_ok!(client.delete_all(vec![key, imported_key]).await);
+}
@@ -0,0 +1,149 @@
+use std::convert::TryFrom;
+
+use chrono::{TimeZone, Utc};
+
+use crate::datastore::api::read_options::{ConsistencyType, ReadConsistency as ApiConsistency};
+use crate::datastore::api::{self, filter::FilterType};
+use crate::datastore::{
+    convert_query, convert_read_options, AggregationResult, Entity, Filter, IntoValue, Query,
+    QueryBatch, ReadConsistency, ReadOptions,
+};
+
+/// Renders a converted filter tree, like `AND(OR(a = 1, b IN [2, 3]))`.
+fn render(filter: &api::Filter) -> String {
+    use api::composite_filter::Operator as CompositeOperator;
+    use api::property_filter::Operator;
+    use api::value::ValueType;
+
+    fn render_value(value: &api::Value) -> String {
+        match value.value_type.as_ref() {
+            Some(ValueType::IntegerValue(value)) => value.to_string(),
+            Some(ValueType::StringValue(value)) => format!("{:?}", value),
+            Some(ValueType::ArrayValue(array)) => {
+                let values: Vec<_> = array.values.iter().map(render_value).collect();
+                format!("[{}]", values.join(", "))
+            }
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    match filter.filter_type.as_ref() {
+        Some(FilterType::CompositeFilter(filter)) => {
+            let op = match CompositeOperator::from_i32(filter.op) {
+                Some(CompositeOperator::And) => "AND",
+                Some(CompositeOperator::Or) => "OR",
+                other => panic!("unexpected composite operator: {:?}", other),
+            };
+            let filters: Vec<_> = filter.filters.iter().map(render).collect();
+            format!("{}({})", op, filters.join(", "))
+        }
+        Some(FilterType::PropertyFilter(filter)) => {
+            let op = match Operator::from_i32(filter.op) {
+                Some(Operator::Equal) => "=",
+                Some(Operator::NotEqual) => "!=",
+                Some(Operator::In) => "IN",
+                Some(Operator::NotIn) => "NOT IN",
+                other => panic!("unexpected operator: {:?}", other),
+            };
+            let name = &filter.property.as_ref().unwrap().name;
+            let value = render_value(filter.value.as_ref().unwrap());
+            format!("{} {} {}", name, op, value)
+        }
+        None => panic!("missing filter type"),
+    }
+}
+
+#[test]
+fn datastore_converts_filter_trees_successfully() {
+    //? Nest conjunctions and disjunctions, with the negated operators.
+    let query = Query::new("tasks").filter(Filter::Or(vec![
+        Filter::And(vec![
+            Filter::NotEqual("status".into(), "done".into_value()),
+            Filter::NotIn(
+                "owner".into(),
+                vec!["john".into_value(), "jane".into_value()],
+            ),
+        ]),
+        Filter::Equal("priority".into(), 1.into_value()),
+    ]));
+
+    //? The tree is kept as is, under the conjunction of the query's filters.
+    let converted = convert_query("test-project", query);
+    let filter = converted.filter.expect("missing filter");
+    assert_eq!(
+        render(&filter),
+        r#"AND(OR(AND(status != "done", owner NOT IN ["john", "jane"]), priority = 1))"#
+    );
+}
+
+#[test]
+fn datastore_converts_read_options_successfully() {
+    fn convert(read_options: ReadOptions) -> ConsistencyType {
+        let converted = convert_read_options(read_options);
+        converted
+            .consistency_type
+            .expect("missing consistency type")
+    }
+
+    //? Reads are strongly consistent by default.
+    assert_eq!(ReadOptions::default(), ReadOptions::strong());
+    assert_eq!(
+        convert(ReadOptions::strong()),
+        ConsistencyType::ReadConsistency(ApiConsistency::Strong as i32)
+    );
+    assert_eq!(
+        convert(ReadOptions::eventual()),
+        ConsistencyType::ReadConsistency(ApiConsistency::Eventual as i32)
+    );
+
+    //? Transactional reads refer to the transaction by its ID.
+    let read_options = ReadOptions {
+        consistency: ReadConsistency::Transaction(vec![1, 2, 3]),
+    };
+    assert_eq!(
+        convert(read_options),
+        ConsistencyType::Transaction(vec![1, 2, 3])
+    );
+
+    //? Point-in-time reads keep the sub-second part of the time.
+    let time = Utc.timestamp_opt(1_600_000_000, 123_456_000).unwrap();
+    assert_eq!(
+        convert(ReadOptions::read_time(time)),
+        ConsistencyType::ReadTime(prost_types::Timestamp {
+            seconds: 1_600_000_000,
+            nanos: 123_456_000,
+        })
+    );
+
+    //? Queries carry their read options, with a shorthand for eventual consistency.
+    let query = Query::new("users").eventually_consistent();
+    assert!(query.read_options.is_eventual());
+    let query = Query::new("users").read_options(ReadOptions::read_time(time));
+    assert_eq!(query.read_options, ReadOptions::read_time(time));
+}
+
+#[test]
+fn datastore_rejects_malformed_results_successfully() {
+    //? Results without an entity are reported instead of panicking.
+    let result = api::EntityResult {
+        entity: None,
+        version: 1,
+        cursor: Vec::new(),
+    };
+    assert!(Entity::try_from(result.clone()).is_err());
+    let batch = api::QueryResultBatch {
+        entity_results: vec![result],
+        ..api::QueryResultBatch::default()
+    };
+    assert!(QueryBatch::try_from(batch).is_err());
+}
+
+#[test]
+fn datastore_rejects_malformed_aggregation_results_successfully() {
+    //? Aggregated values without a type are reported instead of panicking.
+    let mut result = api::AggregationResult::default();
+    result
+        .aggregate_properties
+        .insert(String::from("total"), api::Value::default());
+    assert!(AggregationResult::try_from(result).is_err());
+}
@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use futures::TryStreamExt;
+use tonic::Code;
+
+use crate::datastore;
+use crate::datastore::testing::FakeDatastore;
+use crate::datastore::{AggregationQuery, Filter, IntoValue, Key, Mutation, Order, Query};
+use crate::error::Error;
+
+macro_rules! assert_ok {
+    ($expr:expr) => {
+        match $expr {
+            Ok(value) => value,
+            Err(err) => {
+                panic!("asserted result is an error: {}", err);
+            }
+        }
+    };
+}
+
+fn user(name: &str, age: i64) -> HashMap<String, datastore::Value> {
+    let mut values = HashMap::new();
+    values.insert(String::from("name"), name.into_value());
+    values.insert(String::from("age"), age.into_value());
+    values
+}
+
+fn counter(count: i64) -> HashMap<String, i64> {
+    let mut values = HashMap::new();
+    values.insert(String::from("count"), count);
+    values
+}
+
+#[tokio::test]
+async fn fake_puts_data_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store an entity with an incomplete key, which gets allocated.
+    let key = assert_ok!(client.put((Key::new("users"), user("john", 32))).await);
+    let key = key.expect("missing allocated key");
+    assert!(!key.is_incomplete());
+
+    //? Get it back, along with its version.
+    let entity: Option<datastore::Entity> = assert_ok!(client.get(&key).await);
+    let entity = entity.expect("missing entity");
+    assert_eq!(entity.properties(), &user("john", 32).into_value());
+    assert!(entity.version().is_some());
+
+    //? Data is isolated between projects and namespaces.
+    let mut other = assert_ok!(fake.client("other-project").await);
+    let outcome: Option<datastore::Entity> = assert_ok!(other.get(&key).await);
+    assert!(outcome.is_none());
+    let namespaced = key.clone().namespace("test");
+    let outcome: Option<datastore::Entity> = assert_ok!(client.get(&namespaced).await);
+    assert!(outcome.is_none());
+
+    //? Delete it.
+    assert_ok!(client.delete(&key).await);
+    let outcome: Option<datastore::Entity> = assert_ok!(client.get(&key).await);
+    assert!(outcome.is_none());
+}
+
+#[tokio::test]
+async fn fake_runs_queries_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store a family of entities.
+    let parent = Key::new("families").id("smith");
+    let users = [("john", 32), ("jane", 28), ("jack", 12), ("jill", 45)];
+    let entities = users.iter().map(|(name, age)| {
+        let key = Key::new("users").id(*name).parent(parent.clone());
+        (key, user(name, *age))
+    });
+    assert_ok!(client.put_all(entities).await);
+    assert_ok!(
+        client
+            .put((Key::new("users").id("joe"), user("joe", 50)))
+            .await
+    );
+
+    //? Filter and order results.
+    let query = Query::new("users")
+        .filter(Filter::GreaterThanOrEqual("age".into(), 18.into_value()))
+        .order(Order::Desc("age".into()));
+    let entities = assert_ok!(client.query(query).await);
+    let names: Vec<_> = entities
+        .iter()
+        .map(|entity| entity.key().get_id())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            &datastore::KeyID::from("joe"),
+            &datastore::KeyID::from("jill"),
+            &datastore::KeyID::from("john"),
+            &datastore::KeyID::from("jane"),
+        ]
+    );
+
+    //? Only yield the descendants of an ancestor.
+    let query = Query::new("users")
+        .ancestor(parent.clone())
+        .filter(Filter::In(
+            "name".into(),
+            vec!["jack".into_value(), "joe".into_value()],
+        ));
+    let keys = assert_ok!(client.query_keys(query).await);
+    assert_eq!(keys, vec![Key::new("users").id("jack").parent(parent)]);
+
+    //? Count the matching entities.
+    let count = assert_ok!(client.count(Query::new("users")).await);
+    assert_eq!(count, 5);
+}
+
+#[tokio::test]
+async fn fake_paginates_queries_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store more entities than fit in a single batch.
+    let entities = (1..=500).map(|id| (Key::new("pages").id(id), user("page", id)));
+    assert_ok!(client.put_all(entities).await);
+    let entities = assert_ok!(client.query(Query::new("pages")).await);
+    assert_eq!(entities.len(), 500);
+
+    //? Fetch the first page, and resume from its cursor.
+    let query = Query::new("pages").order(Order::Asc("age".into())).limit(2);
+    let mut batches = client.query_batches(query.clone());
+    let first = assert_ok!(batches.try_next().await).expect("missing first page");
+    assert_eq!(first.entities().len(), 2);
+    assert!(first.has_more_results());
+
+    let cursor = first.end_cursor().clone();
+    let second = assert_ok!(client.query(query.start_cursor(cursor)).await);
+    let keys: Vec<_> = second
+        .into_iter()
+        .map(datastore::Entity::into_key)
+        .collect();
+    assert_eq!(keys, vec![Key::new("pages").id(3), Key::new("pages").id(4)]);
+}
+
+#[tokio::test]
+async fn fake_runs_transactions_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+    let key = Key::new("counters").id("visits");
+
+    //? Increment a counter within a transaction.
+    for _ in 0..2 {
+        let key = key.clone();
+        assert_ok!(
+            client
+                .run_in_transaction(|mut tx| {
+                    let key = key.clone();
+                    async move {
+                        let current: Option<HashMap<String, i64>> = tx.get(&key).await?;
+                        let count = current.map_or(0, |values| values["count"]);
+                        tx.put((key, counter(count + 1)))?;
+                        Ok(())
+                    }
+                })
+                .await
+        );
+    }
+    let current: Option<HashMap<String, i64>> = assert_ok!(client.get(&key).await);
+    assert_eq!(current, Some(counter(2)));
+
+    //? A concurrent write to an entity read by a transaction aborts it.
+    let mut tx = assert_ok!(client.begin_transaction().await);
+    let current: Option<HashMap<String, i64>> = assert_ok!(tx.get(&key).await);
+    assert_ok!(client.put((key.clone(), counter(10))).await);
+    assert_ok!(tx.put((
+        key.clone(),
+        counter(current.map_or(0, |values| values["count"]) + 1)
+    )));
+    let outcome = tx.commit().await;
+    assert!(matches!(outcome, Err(Error::Status(status)) if status.code() == Code::Aborted));
+    let current: Option<HashMap<String, i64>> = assert_ok!(client.get(&key).await);
+    assert_eq!(current, Some(counter(10)));
+
+    //? Conditional writes detect conflicts.
+    let result = client.put_if_version((key.clone(), counter(20)), 1).await;
+    assert!(matches!(result, Err(Error::Conflict(_))));
+}
+
+#[tokio::test]
+async fn fake_allocates_ids_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Allocate IDs for incomplete keys.
+    let keys = vec![Key::new("users"), Key::new("users").namespace("test")];
+    let keys = assert_ok!(client.allocate_ids(keys).await);
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|key| !key.is_incomplete()));
+    assert_ne!(keys[0].get_id(), keys[1].get_id());
+    assert_eq!(keys[1].get_namespace(), Some("test"));
+
+    //? Allocating IDs for complete keys is rejected.
+    let outcome = client.allocate_ids(vec![Key::new("users").id(1)]).await;
+    assert!(outcome.is_err());
+}
+
+#[tokio::test]
+async fn fake_is_detected_as_an_emulator_successfully() {
+    //? Setup fake, and designate it as the emulator to use.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    std::env::set_var("DATASTORE_EMULATOR_HOST", fake.address().to_string());
+
+    //? The default constructor connects to it, without credentials.
+    let mut client = assert_ok!(datastore::Client::new("test-project").await);
+    std::env::remove_var("DATASTORE_EMULATOR_HOST");
+    let key = Key::new("users").id("john");
+    assert_ok!(client.put((key.clone(), user("john", 32))).await);
+
+    //? The data is visible through any other client connected to the fake.
+    let mut other = assert_ok!(fake.client("test-project").await);
+    let outcome: Option<datastore::Entity> = assert_ok!(other.get(&key).await);
+    assert!(outcome.is_some());
+}
+
+#[tokio::test]
+async fn fake_honours_ancestors_and_keys_only_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store a family of entities, along with unrelated entities of the same kind.
+    let parent = Key::new("families").id("smith");
+    let child = Key::new("users").id("john").parent(parent.clone());
+    let grandchild = Key::new("users").id("jack").parent(child.clone());
+    let stranger = Key::new("users").id("joe");
+    let cousin = Key::new("users")
+        .id("jane")
+        .parent(Key::new("families").id("doe"));
+    let keys = [&child, &grandchild, &stranger, &cousin];
+    let entities = keys.iter().map(|key| ((*key).clone(), user("user", 30)));
+    assert_ok!(client.put_all(entities).await);
+
+    //? Only the descendants of the ancestor are returned, at any depth.
+    let query = Query::new("users").ancestor(parent.clone());
+    let entities = assert_ok!(client.query(query).await);
+    let mut keys: Vec<_> = entities
+        .into_iter()
+        .map(datastore::Entity::into_key)
+        .collect();
+    keys.sort_by_key(Key::to_string);
+    assert_eq!(keys, vec![child.clone(), grandchild.clone()]);
+
+    //? Keys-only queries return the entities without their properties.
+    let query = Query::new("users").ancestor(child.clone()).keys_only();
+    let entities = assert_ok!(client.query(query).await);
+    assert_eq!(entities.len(), 2);
+    for entity in entities.iter() {
+        assert_eq!(
+            entity.properties(),
+            &datastore::Value::EntityValue(HashMap::new())
+        );
+    }
+
+    //? Keys are directly returned by `query_keys`.
+    let query = Query::new("users").ancestor(child.clone());
+    let mut keys = assert_ok!(client.query_keys(query).await);
+    keys.sort_by_key(Key::to_string);
+    assert_eq!(keys, vec![child, grandchild]);
+}
+
+#[tokio::test]
+async fn fake_enforces_strict_mutations_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+    let key = Key::new("users").id("john");
+
+    //? Updating a missing entity fails.
+    let mutation = assert_ok!(Mutation::update((key.clone(), user("john", 32))));
+    let outcome = client.commit(vec![mutation]).await;
+    assert!(matches!(outcome, Err(Error::Status(status)) if status.code() == Code::NotFound));
+
+    //? Inserting a missing entity succeeds, but inserting it again fails.
+    let mutation = assert_ok!(Mutation::insert((key.clone(), user("john", 32))));
+    assert_ok!(client.commit(vec![mutation]).await);
+    let mutation = assert_ok!(Mutation::insert((key.clone(), user("john", 40))));
+    let outcome = client.commit(vec![mutation]).await;
+    assert!(matches!(outcome, Err(Error::Status(status)) if status.code() == Code::AlreadyExists));
+
+    //? Updating the now existing entity succeeds, and failed commits left no trace.
+    let mutation = assert_ok!(Mutation::update((key.clone(), user("john", 33))));
+    assert_ok!(client.commit(vec![mutation]).await);
+    let entity: Option<HashMap<String, datastore::Value>> = assert_ok!(client.get(&key).await);
+    assert_eq!(entity, Some(user("john", 33)));
+}
+
+#[tokio::test]
+async fn fake_runs_filter_trees_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store a few users.
+    let users = [("john", 32), ("jane", 28), ("jack", 12), ("jill", 45)];
+    let entities = users
+        .iter()
+        .map(|(name, age)| (Key::new("users").id(*name), user(name, *age)));
+    assert_ok!(client.put_all(entities).await);
+
+    async fn names(client: &mut datastore::Client, filter: Filter) -> Vec<String> {
+        let query = Query::new("users").filter(filter);
+        let entities = assert_ok!(client.query(query).await);
+        let mut names: Vec<_> = entities
+            .iter()
+            .map(|entity| assert_ok!(entity.get::<String>("name")))
+            .collect();
+        names.sort();
+        names
+    }
+
+    //? Disjunctions match any of their filters.
+    let filter = Filter::Or(vec![
+        Filter::Equal("name".into(), "john".into_value()),
+        Filter::LesserThan("age".into(), 18.into_value()),
+    ]);
+    assert_eq!(names(&mut client, filter).await, vec!["jack", "john"]);
+
+    //? Negated filters exclude the matching values.
+    let filter = Filter::NotEqual("name".into(), "john".into_value());
+    assert_eq!(
+        names(&mut client, filter).await,
+        vec!["jack", "jane", "jill"]
+    );
+    let filter = Filter::NotIn(
+        "name".into(),
+        vec!["john".into_value(), "jill".into_value()],
+    );
+    assert_eq!(names(&mut client, filter).await, vec!["jack", "jane"]);
+
+    //? Trees mix conjunctions and disjunctions at any depth.
+    let filter = Filter::Or(vec![
+        Filter::And(vec![
+            Filter::GreaterThan("age".into(), 30.into_value()),
+            Filter::NotEqual("name".into(), "jill".into_value()),
+        ]),
+        Filter::In("name".into(), vec!["jane".into_value()]),
+    ]);
+    assert_eq!(names(&mut client, filter).await, vec!["jane", "john"]);
+}
+
+#[tokio::test]
+async fn fake_runs_aggregations_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store a few users.
+    let users = [("john", 32), ("jane", 28), ("jack", 12), ("jill", 45)];
+    let entities = users
+        .iter()
+        .map(|(name, age)| (Key::new("users").id(*name), user(name, *age)));
+    assert_ok!(client.put_all(entities).await);
+
+    //? Run multiple aggregations at once, under their own aliases.
+    let query = Query::new("users").filter(Filter::GreaterThan("age".into(), 18.into_value()));
+    let query = AggregationQuery::new(query)
+        .count("adults")
+        .count_up_to("capped", 2)
+        .sum("total_age", "age")
+        .avg("average_age", "age");
+    let result = assert_ok!(client.aggregate(query).await);
+    assert_eq!(result.properties().len(), 4);
+    assert_eq!(result.count("adults"), Some(3));
+    assert_eq!(result.count("capped"), Some(2));
+    assert_eq!(result.get("total_age"), Some(&105.into_value()));
+    assert_eq!(result.number("average_age"), Some(35.0));
+    assert_eq!(result.get("missing"), None);
+
+    //? Averages over no values are null, while sums are zero.
+    let query = AggregationQuery::new(Query::new("users"))
+        .sum("total_score", "score")
+        .avg("average_score", "score");
+    let result = assert_ok!(client.aggregate(query).await);
+    assert_eq!(result.number("total_score"), Some(0.0));
+    assert_eq!(
+        result.get("average_score"),
+        Some(&datastore::Value::NullValue)
+    );
+}
+
+#[tokio::test]
+async fn fake_looks_up_many_keys_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+
+    //? Store more entities than fit in a single lookup.
+    let entities = (1..=2500).map(|id| (Key::new("counters").id(id), counter(id)));
+    assert_ok!(client.put_all(entities).await);
+
+    //? Results are aligned with the keys, across the batches of lookups.
+    let keys: Vec<_> = (0..=2500)
+        .rev()
+        .map(|id| Key::new("counters").id(id))
+        .collect();
+    let values: Vec<Option<HashMap<String, i64>>> = assert_ok!(client.get_all_aligned(&keys).await);
+    assert_eq!(values.len(), 2501);
+    assert_eq!(values[0], Some(counter(2500)));
+    assert_eq!(values[1499], Some(counter(1001)));
+    assert_eq!(values[2500], None);
+}
+
+#[tokio::test]
+async fn fake_bulk_writes_successfully() {
+    //? Setup fake and client.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut client = assert_ok!(fake.client("test-project").await);
+    let writer = client.bulk_writer().max_mutations(100).concurrency(4);
+
+    //? Write entities over multiple commits, getting back keys in order.
+    let entities = (1..=250).map(|id| (Key::new("counters").id(id), counter(id)));
+    let result = assert_ok!(writer.put_all(entities).await);
+    assert!(result.is_success());
+    let keys: Vec<_> = result.keys().cloned().collect();
+    assert_eq!(keys.len(), 250);
+    assert_eq!(keys[249], Key::new("counters").id(250));
+    assert_eq!(assert_ok!(client.count(Query::new("counters")).await), 250);
+
+    //? Delete them from borrowed keys.
+    let result = writer.delete_all(&keys).await;
+    assert!(result.is_success());
+    assert_eq!(assert_ok!(client.count(Query::new("counters")).await), 0);
+
+    //? Failed commits hand back their mutations, to retry them for instance.
+    let mutations = vec![
+        assert_ok!(Mutation::update((Key::new("counters").id(1), counter(1)))),
+        assert_ok!(Mutation::update((Key::new("counters").id(2), counter(2)))),
+    ];
+    let result = writer.commit(mutations.clone()).await;
+    assert_eq!(result.failures().len(), 1);
+    assert_eq!(result.failures()[0].mutations(), mutations.as_slice());
+}
+
+#[tokio::test]
+async fn fake_imports_into_another_project_successfully() {
+    //? Setup fake and clients.
+    let fake = assert_ok!(FakeDatastore::start().await);
+    let mut source = assert_ok!(fake.client("test-project").await);
+    let mut target = assert_ok!(fake.client("other-project").await);
+
+    //? Export entities from the source project.
+    let entities = (1..=5).map(|id| (Key::new("counters").id(id), counter(id)));
+    assert_ok!(source.put_all(entities).await);
+    let mut exporter = datastore::io::Exporter::new(Vec::new());
+    let stream = source.query_stream(Query::new("counters"));
+    assert_eq!(assert_ok!(exporter.export(stream).await), 5);
+    let output = exporter.into_inner();
+
+    //? Import them into the target project, the source project staying unchanged.
+    let importer = datastore::io::Importer::new(source.bulk_writer()).project("other-project");
+    let result = importer.import(output.as_slice()).await;
+    assert!(result.is_success());
+    assert_eq!(result.written(), 5);
+    assert_eq!(assert_ok!(target.count(Query::new("counters")).await), 5);
+    assert_eq!(assert_ok!(source.count(Query::new("counters")).await), 5);
+
+    //? Malformed lines stop the import, the entities read before them being written.
+    let mut input = output;
+    input.extend_from_slice(b"not json\n");
+    let importer = datastore::io::Importer::new(source.bulk_writer()).namespace("broken");
+    let result = importer.import(input.as_slice()).await;
+    assert!(!result.is_success());
+    assert!(result.error().is_some());
+    assert_eq!(result.written(), 5);
+    let query = Query::new("counters").namespace("broken");
+    assert_eq!(assert_ok!(source.count(query).await), 5);
+}
@@ -1,5 +1,9 @@
 #[cfg(feature = "datastore")]
 mod datastore;
+#[cfg(feature = "datastore")]
+mod datastore_conversions;
+#[cfg(feature = "datastore-testing")]
+mod datastore_fake;
 #[cfg(feature = "pubsub")]
 mod pubsub;
 #[cfg(feature = "storage")]